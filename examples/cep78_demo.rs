@@ -34,10 +34,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("\n");
     std::thread::sleep(std::time::Duration::from_secs(1));
 
-    let fetcher = Fetcher {
-        client: CasperClient::default_mainnet(),
-        ces_metadata: metadata,
-    };
+    let fetcher = Fetcher::new(CasperClient::default_mainnet(), metadata);
 
     println!("Extracting schema:");
     std::thread::sleep(std::time::Duration::from_secs(1));
@@ -73,15 +70,16 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("Fetching event {}:", event_id);
     std::thread::sleep(std::time::Duration::from_secs(1));
 
-    let dynamic_event = fetcher.fetch_event(event_id, &schemas).await?;
+    let contextual_event = fetcher.fetch_event(event_id, &schemas).await?;
 
-    println!("-> {:?}", dynamic_event);
+    println!("-> {:?}", contextual_event);
     std::thread::sleep(std::time::Duration::from_secs(1));
     println!("\n");
 
     println!("Parsing event {}:", event_id);
     std::thread::sleep(std::time::Duration::from_secs(1));
 
+    let dynamic_event = contextual_event.event;
     match dynamic_event.name.as_str() {
         "Mint" => {
             let data = dynamic_event.to_ces_bytes()?;
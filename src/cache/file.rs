@@ -0,0 +1,265 @@
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{BufReader, ErrorKind, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::sync::Mutex;
+
+use casper_event_standard::casper_types::bytesrepr::{FromBytes, ToBytes};
+use casper_event_standard::casper_types::URefAddr;
+use casper_event_standard::Schemas;
+use casper_hashing::Digest;
+
+use super::EventCache;
+use crate::error::ToolkitError;
+
+// Record tags.
+const EVENT_RECORD_TAG: u8 = 0;
+const SCHEMA_RECORD_TAG: u8 = 1;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum RecordKey {
+    Event(URefAddr, u32),
+    Schema(URefAddr, [u8; Digest::LENGTH]),
+}
+
+/// Cache persisted as an append-only file.
+///
+/// Every record consists of a tag, URef address, event ID or state root hash, data length and
+/// the data itself. Index of the records is rebuilt when the file is opened, and a record
+/// left half-written e.g. by a crash is dropped.
+#[derive(Debug)]
+pub struct FileEventCache {
+    inner: Mutex<FileState>,
+}
+
+#[derive(Debug)]
+struct FileState {
+    file: File,
+    // Offset and length of the data of every record.
+    index: HashMap<RecordKey, (u64, u32)>,
+    end: u64,
+}
+
+impl FileEventCache {
+    /// Opens the cache file, creating it if missing.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, ToolkitError> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)?;
+
+        let mut index = HashMap::new();
+        let mut reader = BufReader::new(&file);
+        let file_length = file.metadata()?.len();
+        let mut end = 0;
+        while let Some((key, length)) = read_record_header(&mut reader)? {
+            let data_offset = reader.stream_position()?;
+            let record_end = data_offset + u64::from(length);
+            if record_end > file_length {
+                break;
+            }
+            reader.seek(SeekFrom::Start(record_end))?;
+            index.insert(key, (data_offset, length));
+            end = record_end;
+        }
+        drop(reader);
+        file.set_len(end)?;
+
+        Ok(Self {
+            inner: Mutex::new(FileState { file, index, end }),
+        })
+    }
+
+    fn load(&self, key: &RecordKey) -> Result<Option<Vec<u8>>, ToolkitError> {
+        let mut state = self.inner.lock().unwrap();
+        let Some((offset, length)) = state.index.get(key).copied() else {
+            return Ok(None);
+        };
+        let mut data = vec![0; length as usize];
+        state.file.seek(SeekFrom::Start(offset))?;
+        state.file.read_exact(&mut data)?;
+
+        Ok(Some(data))
+    }
+
+    fn save(&self, key: RecordKey, data: &[u8]) -> Result<(), ToolkitError> {
+        let mut state = self.inner.lock().unwrap();
+        if state.index.contains_key(&key) {
+            return Ok(());
+        }
+        let length = u32::try_from(data.len()).map_err(|_e| ToolkitError::SerializationError {
+            context: "cache record",
+        })?;
+
+        let mut record = record_header(&key);
+        record.extend_from_slice(&length.to_le_bytes());
+        let data_offset = state.end + record.len() as u64;
+        record.extend_from_slice(data);
+
+        let end = state.end;
+        state.file.seek(SeekFrom::Start(end))?;
+        state.file.write_all(&record)?;
+        state.file.flush()?;
+        state.end += record.len() as u64;
+        state.index.insert(key, (data_offset, length));
+
+        Ok(())
+    }
+}
+
+impl EventCache for FileEventCache {
+    fn load_event(
+        &self,
+        events_data: &URefAddr,
+        event_id: u32,
+    ) -> Result<Option<Vec<u8>>, ToolkitError> {
+        self.load(&RecordKey::Event(*events_data, event_id))
+    }
+
+    fn save_event(
+        &self,
+        events_data: &URefAddr,
+        event_id: u32,
+        event_bytes: &[u8],
+    ) -> Result<(), ToolkitError> {
+        self.save(RecordKey::Event(*events_data, event_id), event_bytes)
+    }
+
+    fn load_schema(
+        &self,
+        events_schema: &URefAddr,
+        state_root_hash: &Digest,
+    ) -> Result<Option<Schemas>, ToolkitError> {
+        let key = RecordKey::Schema(*events_schema, state_root_hash.value());
+        let Some(schemas_bytes) = self.load(&key)? else {
+            return Ok(None);
+        };
+        let (schemas, _) = Schemas::from_bytes(&schemas_bytes).map_err(|_e| {
+            ToolkitError::DeserializationError {
+                context: "cached schema",
+            }
+        })?;
+
+        Ok(Some(schemas))
+    }
+
+    fn save_schema(
+        &self,
+        events_schema: &URefAddr,
+        state_root_hash: &Digest,
+        schemas: &Schemas,
+    ) -> Result<(), ToolkitError> {
+        let schemas_bytes = schemas
+            .to_bytes()
+            .map_err(|_e| ToolkitError::SerializationError {
+                context: "cached schema",
+            })?;
+
+        let key = RecordKey::Schema(*events_schema, state_root_hash.value());
+        self.save(key, &schemas_bytes)
+    }
+}
+
+fn record_header(key: &RecordKey) -> Vec<u8> {
+    let mut header = vec![];
+    match key {
+        RecordKey::Event(uref_addr, event_id) => {
+            header.push(EVENT_RECORD_TAG);
+            header.extend_from_slice(uref_addr);
+            header.extend_from_slice(&event_id.to_le_bytes());
+        }
+        RecordKey::Schema(uref_addr, state_root_hash) => {
+            header.push(SCHEMA_RECORD_TAG);
+            header.extend_from_slice(uref_addr);
+            header.extend_from_slice(state_root_hash);
+        }
+    }
+    header
+}
+
+// Returns `None` at the end of the file, or at the record left half-written.
+fn read_record_header(reader: &mut impl Read) -> Result<Option<(RecordKey, u32)>, ToolkitError> {
+    match try_read_record_header(reader) {
+        Ok(header) => Ok(Some(header)),
+        Err(ToolkitError::IoError { error }) if error.kind() == ErrorKind::UnexpectedEof => {
+            Ok(None)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+fn try_read_record_header(reader: &mut impl Read) -> Result<(RecordKey, u32), ToolkitError> {
+    let mut tag = [0; 1];
+    reader.read_exact(&mut tag)?;
+    let mut uref_addr = URefAddr::default();
+    reader.read_exact(&mut uref_addr)?;
+
+    let key = match tag[0] {
+        EVENT_RECORD_TAG => {
+            let mut event_id = [0; 4];
+            reader.read_exact(&mut event_id)?;
+            RecordKey::Event(uref_addr, u32::from_le_bytes(event_id))
+        }
+        SCHEMA_RECORD_TAG => {
+            let mut state_root_hash = [0; Digest::LENGTH];
+            reader.read_exact(&mut state_root_hash)?;
+            RecordKey::Schema(uref_addr, state_root_hash)
+        }
+        _ => {
+            return Err(ToolkitError::DeserializationError {
+                context: "cache record tag",
+            })
+        }
+    };
+    let mut length = [0; 4];
+    reader.read_exact(&mut length)?;
+
+    Ok((key, u32::from_le_bytes(length)))
+}
+
+#[cfg(test)]
+mod tests {
+    use casper_event_standard::Schema;
+
+    use super::*;
+
+    #[test]
+    fn test_file_event_cache_roundtrip() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("event-cache");
+        let events_data = [1; 32];
+        let state_root_hash = Digest::from([2; 32]);
+        let mut schemas = Schemas::new();
+        schemas.0.insert("Transfer".to_string(), Schema::new());
+
+        let cache = FileEventCache::open(&path).unwrap();
+        assert_eq!(cache.load_event(&events_data, 0).unwrap(), None);
+        cache.save_event(&events_data, 0, &[1, 2, 3]).unwrap();
+        cache.save_event(&events_data, 1, &[4]).unwrap();
+        cache
+            .save_schema(&events_data, &state_root_hash, &schemas)
+            .unwrap();
+        drop(cache);
+
+        // Half-written record is dropped on open.
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(&[EVENT_RECORD_TAG, 1, 1]).unwrap();
+        drop(file);
+
+        let cache = FileEventCache::open(&path).unwrap();
+        assert_eq!(
+            cache.load_event(&events_data, 0).unwrap(),
+            Some(vec![1, 2, 3])
+        );
+        assert_eq!(cache.load_event(&events_data, 1).unwrap(), Some(vec![4]));
+        assert_eq!(cache.load_event(&[3; 32], 1).unwrap(), None);
+        assert_eq!(
+            cache.load_schema(&events_data, &state_root_hash).unwrap(),
+            Some(schemas)
+        );
+        cache.save_event(&events_data, 2, &[5]).unwrap();
+        assert_eq!(cache.load_event(&events_data, 2).unwrap(), Some(vec![5]));
+    }
+}
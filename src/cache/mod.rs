@@ -0,0 +1,101 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use casper_event_standard::casper_types::URefAddr;
+use casper_event_standard::Schemas;
+use casper_hashing::Digest;
+
+use crate::error::ToolkitError;
+
+mod file;
+
+pub use file::FileEventCache;
+
+/// Storage of immutable event data, consulted by `Fetcher` before reading the global state.
+///
+/// Events are keyed by the address of the events dictionary URef, and schemas by the address of
+/// the schema URef and the state root hash. Event stored under given ID never changes, and
+/// neither does the schema at given state root hash, so cached data never expires.
+pub trait EventCache: Send + Sync {
+    /// Loads raw event bytes, as stored in the events dictionary.
+    fn load_event(
+        &self,
+        events_data: &URefAddr,
+        event_id: u32,
+    ) -> Result<Option<Vec<u8>>, ToolkitError>;
+
+    fn save_event(
+        &self,
+        events_data: &URefAddr,
+        event_id: u32,
+        event_bytes: &[u8],
+    ) -> Result<(), ToolkitError>;
+
+    fn load_schema(
+        &self,
+        events_schema: &URefAddr,
+        state_root_hash: &Digest,
+    ) -> Result<Option<Schemas>, ToolkitError>;
+
+    fn save_schema(
+        &self,
+        events_schema: &URefAddr,
+        state_root_hash: &Digest,
+        schemas: &Schemas,
+    ) -> Result<(), ToolkitError>;
+}
+
+/// Cache kept in memory - data is lost on restart.
+#[derive(Debug, Default)]
+pub struct MemoryEventCache {
+    events: Mutex<HashMap<(URefAddr, u32), Vec<u8>>>,
+    schemas: Mutex<HashMap<(URefAddr, Digest), Schemas>>,
+}
+
+impl MemoryEventCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl EventCache for MemoryEventCache {
+    fn load_event(
+        &self,
+        events_data: &URefAddr,
+        event_id: u32,
+    ) -> Result<Option<Vec<u8>>, ToolkitError> {
+        let events = self.events.lock().unwrap();
+        Ok(events.get(&(*events_data, event_id)).cloned())
+    }
+
+    fn save_event(
+        &self,
+        events_data: &URefAddr,
+        event_id: u32,
+        event_bytes: &[u8],
+    ) -> Result<(), ToolkitError> {
+        let mut events = self.events.lock().unwrap();
+        events.insert((*events_data, event_id), event_bytes.to_vec());
+        Ok(())
+    }
+
+    fn load_schema(
+        &self,
+        events_schema: &URefAddr,
+        state_root_hash: &Digest,
+    ) -> Result<Option<Schemas>, ToolkitError> {
+        let schemas = self.schemas.lock().unwrap();
+        Ok(schemas.get(&(*events_schema, *state_root_hash)).cloned())
+    }
+
+    fn save_schema(
+        &self,
+        events_schema: &URefAddr,
+        state_root_hash: &Digest,
+        schemas: &Schemas,
+    ) -> Result<(), ToolkitError> {
+        let mut cached_schemas = self.schemas.lock().unwrap();
+        cached_schemas.insert((*events_schema, *state_root_hash), schemas.clone());
+        Ok(())
+    }
+}
@@ -1,5 +1,14 @@
+use std::sync::Arc;
+
 use thiserror::Error;
 
+// Node errors caused by its temporary state - query failed to execute e.g. on overload, and
+// state root hash not known yet e.g. by a node lagging behind.
+const RETRYABLE_RPC_ERROR_CODES: [i64; 2] = [-32004, -32012];
+
+// Node error of query that found no value under the key.
+const QUERY_FAILED_RPC_ERROR_CODE: i64 = -32003;
+
 #[derive(Error, Debug)]
 pub enum ToolkitError {
     /// CES metadata not found in named keys.
@@ -21,11 +30,33 @@ pub enum ToolkitError {
     #[error("stored value type invalid, expected {expected_type}")]
     UnexpectedStoredValueType { expected_type: &'static str },
 
-    /// Unable to get data from RPC.
+    /// Unable to get data from RPC. Boxed, as the client error is much larger than the others.
     #[error("rpc error: {error}")]
     RpcError {
+        #[source]
+        error: Box<casper_client::Error>,
+    },
+
+    /// Unable to get data from HTTP endpoint.
+    #[error("http error: {error}")]
+    HttpError {
+        #[from]
+        error: reqwest::Error,
+    },
+
+    /// Request reading multiple values failed as a whole. Shared by all the values.
+    #[error("batch request failed: {0}")]
+    BatchRequestFailed(Arc<ToolkitError>),
+
+    /// Call did not finish before the deadline.
+    #[error("deadline exceeded")]
+    Timeout,
+
+    /// Unable to access local storage.
+    #[error("io error: {error}")]
+    IoError {
         #[from]
-        error: casper_client::Error,
+        error: std::io::Error,
     },
 
     /// Unable to deserialize data.
@@ -36,6 +67,22 @@ pub enum ToolkitError {
     #[error("serialization error for '{context}'")]
     SerializationError { context: &'static str },
 
+    /// Value not found in the global state.
+    #[error("stored value not found: {context}")]
+    MissingStoredValue { context: String },
+
+    /// Contract package has no matching contract version.
+    #[error("contract version not found: {context}")]
+    MissingContractVersion { context: String },
+
+    /// Block not found on the node.
+    #[error("block not found")]
+    MissingBlock,
+
+    /// Deploy not found on the node.
+    #[error("deploy not found")]
+    MissingDeploy,
+
     /// Expected a successful deploy.
     #[error("failed deploy")]
     FailedDeployError,
@@ -44,11 +91,100 @@ pub enum ToolkitError {
     #[error("event '{0}' not found in schema")]
     MissingEventSchema(String),
 
+    /// Events stored in the format of unknown CES version.
+    #[error("ces version '{0}' not supported")]
+    UnsupportedCesVersion(String),
+
     /// Event name without required prefix.
     #[error("event prefix not found")]
     MissingEventPrefix,
 
+    /// Data source does not implement the operation.
+    #[error("operation not supported by the source: {operation}")]
+    UnsupportedOperation { operation: &'static str },
+
+    /// Not enough nodes returned identical data.
+    #[error("quorum not reached: {agreeing_nodes} agreeing nodes, {quorum} required")]
+    QuorumNotReached {
+        agreeing_nodes: usize,
+        quorum: usize,
+    },
+
+    /// Merkle proof returned by the node does not match the state root hash.
+    #[error("merkle proof invalid: {context}")]
+    InvalidMerkleProof { context: &'static str },
+
     /// Unexpected error - should NEVER happen.
     #[error("unexpected error: {context}")]
     UnexpectedError { context: String },
 }
+
+impl ToolkitError {
+    /// Whether the failed operation may succeed when repeated e.g. network failure or node
+    /// overload. Errors returned by the node itself, or caused by invalid data, are permanent -
+    /// except for the ones caused by the node's temporary state.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            ToolkitError::RpcError { error } => match error.as_ref() {
+                casper_client::Error::FailedToGetResponse { error, .. }
+                | casper_client::Error::ResponseIsHttpError { error, .. } => {
+                    is_retryable_http_error(error)
+                }
+                casper_client::Error::ResponseIsRpcError { error, .. } => {
+                    RETRYABLE_RPC_ERROR_CODES.contains(&error.code)
+                }
+                _ => false,
+            },
+            ToolkitError::HttpError { error } => is_retryable_http_error(error),
+            ToolkitError::BatchRequestFailed(error) => error.is_retryable(),
+            ToolkitError::Timeout => true,
+            _ => false,
+        }
+    }
+
+    /// Whether the value is not present in the global state, e.g. not created yet.
+    pub(crate) fn is_missing_value(&self) -> bool {
+        match self {
+            ToolkitError::MissingStoredValue { .. } => true,
+            ToolkitError::RpcError { error } => matches!(
+                error.as_ref(),
+                casper_client::Error::ResponseIsRpcError { error, .. }
+                    if error.code == QUERY_FAILED_RPC_ERROR_CODE
+            ),
+            _ => false,
+        }
+    }
+
+    /// Whether the node answered the batch request, but not with the batch response.
+    pub(crate) fn is_batch_rejection(&self) -> bool {
+        matches!(
+            self,
+            ToolkitError::RpcError { error } if matches!(
+                error.as_ref(),
+                casper_client::Error::ResponseIsRpcError { .. }
+                    | casper_client::Error::InvalidRpcResponse { .. }
+                    | casper_client::Error::FailedToParseResponse { .. }
+            )
+        )
+    }
+}
+
+impl From<casper_client::Error> for ToolkitError {
+    fn from(error: casper_client::Error) -> Self {
+        ToolkitError::RpcError {
+            error: Box::new(error),
+        }
+    }
+}
+
+fn is_retryable_http_error(error: &reqwest::Error) -> bool {
+    match error.status() {
+        Some(status) => {
+            status.is_server_error()
+                || status == reqwest::StatusCode::TOO_MANY_REQUESTS
+                || status == reqwest::StatusCode::REQUEST_TIMEOUT
+        }
+        // Transport errors e.g. refused connection or timeout.
+        None => !error.is_builder() && !error.is_decode(),
+    }
+}
@@ -1,10 +1,11 @@
 use casper_event_standard::casper_types;
 use casper_event_standard::Schema;
+use casper_hashing::Digest;
 use casper_types::{
     bytesrepr::{
         FromBytes, ToBytes, OPTION_NONE_TAG, OPTION_SOME_TAG, RESULT_ERR_TAG, RESULT_OK_TAG,
     },
-    CLType, CLValue,
+    CLType, CLValue, HashAddr,
 };
 
 use crate::error::ToolkitError;
@@ -15,6 +16,35 @@ pub struct Event {
     pub fields: Vec<(String, CLValue)>,
 }
 
+/// Origin of the event.
+///
+/// Pair of `contract_hash` and `event_id` uniquely identifies the event, so it can be used for
+/// deduplication. Fields that are not known from the event source are left empty e.g. events
+/// fetched from the global state directly do not carry deploy or block data.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EventContext {
+    /// Contract that emitted the event.
+    pub contract_hash: Option<HashAddr>,
+    /// Event ID, which is incremented with every emitted event of the contract.
+    pub event_id: u32,
+    /// Deploy that emitted the event.
+    pub deploy_hash: Option<Digest>,
+    /// Block containing the deploy.
+    pub block_hash: Option<Digest>,
+    pub block_height: Option<u64>,
+    /// Block timestamp - milliseconds since UNIX epoch.
+    pub block_timestamp: Option<u64>,
+    /// Position of the event write in the deploy execution effects.
+    pub transform_index: Option<u32>,
+}
+
+/// Event enriched with its context.
+#[derive(Debug)]
+pub struct ContextualEvent {
+    pub context: EventContext,
+    pub event: Event,
+}
+
 impl Event {
     pub fn to_ces_bytes(&self) -> Result<Vec<u8>, ToolkitError> {
         let mut result: Vec<u8> = vec![];
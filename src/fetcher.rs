@@ -1,25 +1,506 @@
+use std::ops::Range;
+use std::sync::Arc;
+use std::time::Duration;
+
+pub use casper_client::rpcs::common::BlockIdentifier;
 pub use casper_event_standard::Schemas;
 
 use casper_event_standard::casper_types;
-use casper_types::bytesrepr::FromBytes;
+use casper_hashing::Digest;
+use futures::stream::{self, Stream, StreamExt};
 
+use crate::cache::EventCache;
 use crate::error::ToolkitError;
-use crate::event::Event;
+use crate::event::{ContextualEvent, EventContext};
 use crate::metadata::CesMetadataRef;
-use crate::parser::{parse_event, parse_raw_event_name_and_data};
+use crate::parser::{
+    parse_event, parse_events_from_transforms, parse_versioned_event_name_and_data,
+};
 use crate::rpc::client::CasperClient;
+use crate::rpc::compat;
+use crate::rpc::types::{BlockInfo, DeployExecution};
+use crate::source::GlobalStateSource;
 use crate::utils::parse_hash;
 
-pub struct Fetcher {
-    pub client: CasperClient,
+/// Default number of dictionary reads performed in parallel by `Fetcher::fetch_events`.
+pub const DEFAULT_FETCH_CONCURRENCY: usize = 16;
+
+/// Default interval between events count checks done by `Fetcher::stream_events`.
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(10);
+
+pub struct Fetcher<S = CasperClient> {
+    pub client: S,
     // Metdadata
     pub ces_metadata: CesMetadataRef,
+    // Cache of events and schemas, consulted before reading the global state.
+    event_cache: Option<Arc<dyn EventCache>>,
 }
 
-impl Fetcher {
+impl<S> Fetcher<S> {
+    pub fn new(client: S, ces_metadata: CesMetadataRef) -> Self {
+        Self {
+            client,
+            ces_metadata,
+            event_cache: None,
+        }
+    }
+
+    /// Consults the given cache before reading events and schemas from the global state.
+    ///
+    /// Schemas read at the latest state root hash are not cached, as it changes with every
+    /// block.
+    pub fn with_event_cache(mut self, event_cache: Arc<dyn EventCache>) -> Self {
+        self.event_cache = Some(event_cache);
+        self
+    }
+}
+
+impl<S: GlobalStateSource> Fetcher<S> {
+    /// Pins all the reads to the given state root hash.
+    pub fn at_state_root(&self, state_root_hash: Digest) -> FetcherSnapshot<'_, S> {
+        FetcherSnapshot {
+            fetcher: self,
+            state_root_hash,
+            latest: false,
+        }
+    }
+
+    /// Pins all the reads to the state root hash of the block at given height.
+    pub async fn at_block_height(
+        &self,
+        height: u64,
+    ) -> Result<FetcherSnapshot<'_, S>, ToolkitError> {
+        let block_identifier = BlockIdentifier::Height(height);
+        let state_root_hash = self
+            .client
+            .get_state_root_hash(Some(block_identifier))
+            .await?;
+
+        Ok(self.at_state_root(state_root_hash))
+    }
+
+    /// Pins all the reads to the latest state root hash.
+    pub async fn at_latest(&self) -> Result<FetcherSnapshot<'_, S>, ToolkitError> {
+        let state_root_hash = self.client.get_state_root_hash(None).await?;
+
+        Ok(self.at_latest_state_root(state_root_hash))
+    }
+
+    /// Pins all the reads to the given state root hash, known to be the latest one.
+    pub(crate) fn at_latest_state_root(&self, state_root_hash: Digest) -> FetcherSnapshot<'_, S> {
+        FetcherSnapshot {
+            latest: true,
+            ..self.at_state_root(state_root_hash)
+        }
+    }
+
     pub async fn fetch_events_count(&self) -> Result<u32, ToolkitError> {
-        let events_length_uref = &self.ces_metadata.events_length;
-        let events_length_value = self.client.get_stored_clvalue(events_length_uref).await?;
+        self.at_latest().await?.fetch_events_count().await
+    }
+
+    pub async fn fetch_schema(&self) -> Result<Schemas, ToolkitError> {
+        self.at_latest().await?.fetch_schema().await
+    }
+
+    pub async fn fetch_event(
+        &self,
+        id: u32,
+        event_schema: &Schemas,
+    ) -> Result<ContextualEvent, ToolkitError> {
+        // Cached event does not need the state root hash.
+        if let Some(event_bytes) = self.load_cached_event(id)? {
+            return parse_stored_event(&self.ces_metadata, id, &event_bytes, event_schema);
+        }
+
+        self.at_latest().await?.fetch_event(id, event_schema).await
+    }
+
+    /// Fetches events with IDs from the given range, see `fetch_events_with_concurrency`.
+    pub async fn fetch_events(
+        &self,
+        range: Range<u32>,
+        event_schema: &Schemas,
+    ) -> Result<Vec<(u32, Result<ContextualEvent, ToolkitError>)>, ToolkitError> {
+        self.fetch_events_with_concurrency(range, event_schema, DEFAULT_FETCH_CONCURRENCY)
+            .await
+    }
+
+    /// Fetches events with IDs from the given range, running up to `max_concurrency` requests
+    /// at once.
+    ///
+    /// All the reads are pinned to the same state root hash. Results are returned in event ID
+    /// order, and failure of a single event does not abort the whole batch.
+    pub async fn fetch_events_with_concurrency(
+        &self,
+        range: Range<u32>,
+        event_schema: &Schemas,
+        max_concurrency: usize,
+    ) -> Result<Vec<(u32, Result<ContextualEvent, ToolkitError>)>, ToolkitError> {
+        let events = self
+            .at_latest()
+            .await?
+            .fetch_events_with_concurrency(range, event_schema, max_concurrency)
+            .await;
+
+        Ok(events)
+    }
+
+    /// Streams events starting from the given ID, parsed with the schema stored by the contract.
+    ///
+    /// All the events already stored are emitted first, then events count is polled every
+    /// `poll_interval` and new events are emitted as they land. Schema is read again together
+    /// with the new events, so events of types added later are parsed too.
+    ///
+    /// Errors are yielded as stream items. Retryable errors are followed by reading the latest
+    /// state again after the poll interval, so the stream never skips an event. Other errors,
+    /// e.g. event missing in the schema, end the stream.
+    pub fn stream_events(
+        &self,
+        from_id: u32,
+        poll_interval: Duration,
+    ) -> impl Stream<Item = Result<ContextualEvent, ToolkitError>> + '_ {
+        let state = TailState {
+            next_id: from_id,
+            events_count: from_id,
+            snapshot: None,
+            backoff: false,
+            finished: false,
+        };
+
+        stream::unfold(state, move |mut state| async move {
+            if state.finished {
+                return None;
+            }
+            loop {
+                // Wait before retrying a failed read or checking for new events.
+                if state.backoff {
+                    tokio::time::sleep(poll_interval).await;
+                    state.backoff = false;
+                }
+
+                // Emit already known events.
+                if let Some((state_root_hash, event_schema)) = state
+                    .snapshot
+                    .as_ref()
+                    .filter(|_| state.next_id < state.events_count)
+                {
+                    let snapshot = self.at_state_root(*state_root_hash);
+                    return match snapshot.fetch_event(state.next_id, event_schema).await {
+                        Ok(event) => {
+                            state.next_id += 1;
+                            Some((Ok(event), state))
+                        }
+                        Err(e) => {
+                            state = state.failed(&e);
+                            Some((Err(e), state))
+                        }
+                    };
+                }
+
+                // Check if there are new events, reading the schema they were stored with.
+                match self.tail_snapshot(state.next_id).await {
+                    Ok((events_count, snapshot)) => {
+                        state.events_count = events_count;
+                        state.backoff = snapshot.is_none();
+                        state.snapshot = snapshot;
+                    }
+                    Err(e) => {
+                        state = state.failed(&e);
+                        return Some((Err(e), state));
+                    }
+                }
+            }
+        })
+    }
+
+    // Reads the latest events count, and the schema if there are events from the given ID.
+    async fn tail_snapshot(
+        &self,
+        next_id: u32,
+    ) -> Result<(u32, Option<(Digest, Schemas)>), ToolkitError> {
+        let snapshot = self.at_latest().await?;
+        let events_count = snapshot.fetch_events_count().await?;
+        if next_id >= events_count {
+            return Ok((events_count, None));
+        }
+        let event_schema = snapshot.fetch_schema().await?;
+
+        Ok((
+            events_count,
+            Some((snapshot.state_root_hash(), event_schema)),
+        ))
+    }
+
+    pub async fn fetch_events_from_deploy(
+        &self,
+        deploy_hash: &str,
+        event_schema: &Schemas,
+    ) -> Result<Vec<ContextualEvent>, ToolkitError> {
+        // Build deploy hash.
+        let deploy_hash = Digest::from(parse_hash(deploy_hash)?);
+
+        let deploy_execution = self.client.get_deploy_result(deploy_hash).await?;
+        let effects = match deploy_execution.execution_result {
+            casper_types::ExecutionResult::Failure { .. } => Err(ToolkitError::FailedDeployError),
+            casper_types::ExecutionResult::Success { effect, .. } => Ok(effect),
+        }?;
+
+        let mut events =
+            parse_events_from_transforms(&effects.transforms, &self.ces_metadata, event_schema)?;
+
+        // Enrich events with deploy and block data.
+        if !events.is_empty() {
+            let block_identifier = BlockIdentifier::Hash(casper_client::types::BlockHash::new(
+                compat::digest_to_client_types(&deploy_execution.block_hash)?,
+            ));
+            let block = self.client.get_block(block_identifier).await?;
+            set_deploy_context(&mut events, deploy_hash, &block);
+        }
+
+        Ok(events)
+    }
+
+    /// Fetches events emitted by all the deploys in the given block.
+    pub async fn fetch_events_from_block(
+        &self,
+        block_identifier: BlockIdentifier,
+        event_schema: &Schemas,
+    ) -> Result<Vec<DeployEvents>, ToolkitError> {
+        let contracts = [(&self.ces_metadata, event_schema)];
+
+        fetch_contracts_events_from_block(&self.client, &contracts, block_identifier).await
+    }
+
+    fn load_cached_event(&self, id: u32) -> Result<Option<Vec<u8>>, ToolkitError> {
+        match &self.event_cache {
+            Some(cache) => cache.load_event(&self.ces_metadata.events_data.addr(), id),
+            None => Ok(None),
+        }
+    }
+
+    fn save_cached_event(&self, id: u32, event_bytes: &[u8]) -> Result<(), ToolkitError> {
+        match &self.event_cache {
+            Some(cache) => cache.save_event(&self.ces_metadata.events_data.addr(), id, event_bytes),
+            None => Ok(()),
+        }
+    }
+
+    fn load_cached_schema(
+        &self,
+        state_root_hash: &Digest,
+    ) -> Result<Option<Schemas>, ToolkitError> {
+        match &self.event_cache {
+            Some(cache) => {
+                cache.load_schema(&self.ces_metadata.events_schema.addr(), state_root_hash)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn save_cached_schema(
+        &self,
+        state_root_hash: &Digest,
+        schemas: &Schemas,
+    ) -> Result<(), ToolkitError> {
+        match &self.event_cache {
+            Some(cache) => cache.save_schema(
+                &self.ces_metadata.events_schema.addr(),
+                state_root_hash,
+                schemas,
+            ),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Events emitted by a single deploy.
+#[derive(Debug)]
+pub struct DeployEvents {
+    pub deploy_hash: Digest,
+    pub events: Vec<ContextualEvent>,
+}
+
+/// Fetches events of multiple contracts, emitted by all the deploys in the given block.
+///
+/// Only deploys that emitted at least one event are returned, in the block order. Events of
+/// all the given contracts are grouped by deploy, and ordered by their position in the deploy
+/// execution effects.
+pub async fn fetch_contracts_events_from_block<S: GlobalStateSource>(
+    client: &S,
+    contracts: &[(&CesMetadataRef, &Schemas)],
+    block_identifier: BlockIdentifier,
+) -> Result<Vec<DeployEvents>, ToolkitError> {
+    let (block, deploys) = fetch_block_deploys(client, block_identifier).await?;
+
+    let mut contracts_events = vec![];
+    for (ces_metadata, event_schema) in contracts {
+        contracts_events.push(parse_deploys_events(&deploys, ces_metadata, event_schema)?);
+    }
+
+    Ok(group_deploys_events(&block, &deploys, contracts_events))
+}
+
+/// Fetches the block, with execution results of all its deploys in the block order.
+pub(crate) async fn fetch_block_deploys<S: GlobalStateSource>(
+    client: &S,
+    block_identifier: BlockIdentifier,
+) -> Result<(BlockInfo, Vec<(Digest, DeployExecution)>), ToolkitError> {
+    let block = client.get_block(block_identifier).await?;
+
+    let deploys: Vec<Result<(Digest, DeployExecution), ToolkitError>> =
+        stream::iter(block.deploy_hashes.iter().copied())
+            .map(|deploy_hash| async move {
+                let deploy_execution = client.get_deploy_result(deploy_hash).await?;
+                Ok((deploy_hash, deploy_execution))
+            })
+            .buffered(DEFAULT_FETCH_CONCURRENCY)
+            .collect()
+            .await;
+    let deploys = deploys.into_iter().collect::<Result<_, _>>()?;
+
+    Ok((block, deploys))
+}
+
+/// Parses events of the contract emitted by every deploy, in the order of the deploys.
+pub(crate) fn parse_deploys_events(
+    deploys: &[(Digest, DeployExecution)],
+    ces_metadata: &CesMetadataRef,
+    event_schema: &Schemas,
+) -> Result<Vec<Vec<ContextualEvent>>, ToolkitError> {
+    let mut deploys_events = vec![];
+    for (_, deploy_execution) in deploys {
+        // Failed deploys do not emit events.
+        let events = match &deploy_execution.execution_result {
+            casper_types::ExecutionResult::Success { effect, .. } => {
+                parse_events_from_transforms(&effect.transforms, ces_metadata, event_schema)?
+            }
+            casper_types::ExecutionResult::Failure { .. } => vec![],
+        };
+        deploys_events.push(events);
+    }
+
+    Ok(deploys_events)
+}
+
+/// Groups events of multiple contracts, as returned by `parse_deploys_events`, by deploy.
+/// Deploys without events are skipped.
+pub(crate) fn group_deploys_events(
+    block: &BlockInfo,
+    deploys: &[(Digest, DeployExecution)],
+    contracts_events: Vec<Vec<Vec<ContextualEvent>>>,
+) -> Vec<DeployEvents> {
+    let mut grouped: Vec<Vec<ContextualEvent>> = deploys.iter().map(|_| vec![]).collect();
+    for deploys_events in contracts_events {
+        for (events, deploy_events) in grouped.iter_mut().zip(deploys_events) {
+            events.extend(deploy_events);
+        }
+    }
+
+    let mut result = vec![];
+    for ((deploy_hash, _), mut events) in deploys.iter().zip(grouped) {
+        if events.is_empty() {
+            continue;
+        }
+        events.sort_by_key(|event| event.context.transform_index);
+        set_deploy_context(&mut events, *deploy_hash, block);
+        result.push(DeployEvents {
+            deploy_hash: *deploy_hash,
+            events,
+        });
+    }
+
+    result
+}
+
+/// Parses event read from the global state.
+pub(crate) fn parse_stored_event(
+    ces_metadata: &CesMetadataRef,
+    id: u32,
+    event_value_bytes: &[u8],
+    event_schema: &Schemas,
+) -> Result<ContextualEvent, ToolkitError> {
+    let (event_name, event_data) =
+        parse_versioned_event_name_and_data(ces_metadata, event_value_bytes)?;
+
+    // Parse dynamic event data.
+    let dynamic_event = parse_event(event_name, &event_data, event_schema)?;
+
+    // Only contract data is known, when reading from the global state.
+    let context = EventContext {
+        contract_hash: ces_metadata.contract_hash,
+        event_id: id,
+        ..Default::default()
+    };
+
+    Ok(ContextualEvent {
+        context,
+        event: dynamic_event,
+    })
+}
+
+fn set_deploy_context(events: &mut [ContextualEvent], deploy_hash: Digest, block: &BlockInfo) {
+    for ContextualEvent { context, .. } in events.iter_mut() {
+        context.deploy_hash = Some(deploy_hash);
+        context.block_hash = Some(block.hash);
+        context.block_height = Some(block.height);
+        context.block_timestamp = Some(block.timestamp);
+    }
+}
+
+// Progress of `Fetcher::stream_events`.
+struct TailState {
+    next_id: u32,
+    events_count: u32,
+    // State root hash at which `events_count` was read, with the schema read there.
+    snapshot: Option<(Digest, Schemas)>,
+    // Whether to wait for poll interval before next read.
+    backoff: bool,
+    // Whether the permanent error was yielded.
+    finished: bool,
+}
+
+impl TailState {
+    // Schedules reading the latest state again, or ends the stream on permanent error.
+    fn failed(mut self, error: &ToolkitError) -> Self {
+        self.snapshot = None;
+        self.backoff = true;
+        self.finished = !error.is_retryable();
+        self
+    }
+}
+
+/// View of the contract events at a single state root hash.
+///
+/// Every read made through the snapshot refers to the same global state, so e.g. events count,
+/// schema and events data are always consistent with each other.
+pub struct FetcherSnapshot<'a, S = CasperClient> {
+    fetcher: &'a Fetcher<S>,
+    state_root_hash: Digest,
+    // Whether the state root hash was the latest one, when the snapshot was taken.
+    latest: bool,
+}
+
+// Manual impls, as derive would require `S: Clone`.
+impl<'a, S> Clone for FetcherSnapshot<'a, S> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, S> Copy for FetcherSnapshot<'a, S> {}
+
+impl<'a, S: GlobalStateSource> FetcherSnapshot<'a, S> {
+    pub fn state_root_hash(&self) -> Digest {
+        self.state_root_hash
+    }
+
+    pub async fn fetch_events_count(&self) -> Result<u32, ToolkitError> {
+        let events_length_uref = &self.fetcher.ces_metadata.events_length;
+        let events_length_value = self
+            .fetcher
+            .client
+            .get_stored_clvalue(self.state_root_hash, events_length_uref)
+            .await?;
         let events_length: u32 = events_length_value
             .into_t()
             .map_err(|e| ToolkitError::InvalidCLValue(e.to_string()))?;
@@ -28,8 +509,28 @@ impl Fetcher {
     }
 
     pub async fn fetch_schema(&self) -> Result<Schemas, ToolkitError> {
-        let events_schema_uref = &self.ces_metadata.events_schema;
-        let events_schema_value = self.client.get_stored_clvalue(events_schema_uref).await?;
+        // Latest state root hash is rarely read twice, so caching it would only grow the cache.
+        if self.latest {
+            return self.read_schema().await;
+        }
+        if let Some(events_schema) = self.fetcher.load_cached_schema(&self.state_root_hash)? {
+            return Ok(events_schema);
+        }
+
+        let events_schema = self.read_schema().await?;
+        self.fetcher
+            .save_cached_schema(&self.state_root_hash, &events_schema)?;
+
+        Ok(events_schema)
+    }
+
+    async fn read_schema(&self) -> Result<Schemas, ToolkitError> {
+        let events_schema_uref = &self.fetcher.ces_metadata.events_schema;
+        let events_schema_value = self
+            .fetcher
+            .client
+            .get_stored_clvalue(self.state_root_hash, events_schema_uref)
+            .await?;
         let events_schema: Schemas = events_schema_value
             .into_t()
             .map_err(|e| ToolkitError::InvalidCLValue(e.to_string()))?;
@@ -41,67 +542,503 @@ impl Fetcher {
         &self,
         id: u32,
         event_schema: &Schemas,
-    ) -> Result<Event, ToolkitError> {
-        let events_data_uref = &self.ces_metadata.events_data;
+    ) -> Result<ContextualEvent, ToolkitError> {
+        // Cached event may not exist yet at the snapshot state.
+        if let Some(event_bytes) = self.fetcher.load_cached_event(id)? {
+            if id < self.fetch_events_count().await? {
+                return parse_stored_event(
+                    &self.fetcher.ces_metadata,
+                    id,
+                    &event_bytes,
+                    event_schema,
+                );
+            }
+        }
+
+        self.read_event(id, event_schema).await
+    }
+
+    async fn read_event(
+        &self,
+        id: u32,
+        event_schema: &Schemas,
+    ) -> Result<ContextualEvent, ToolkitError> {
+        let events_data_uref = &self.fetcher.ces_metadata.events_data;
         let event_value = self
+            .fetcher
             .client
-            .get_stored_clvalue_from_dict(events_data_uref, &id.to_string())
+            .get_stored_clvalue_from_dict(self.state_root_hash, events_data_uref, &id.to_string())
             .await?;
-        let event_value_bytes = event_value.inner_bytes();
-        let (event_name, event_data) = parse_raw_event_name_and_data(event_value_bytes)?;
+        self.fetcher
+            .save_cached_event(id, event_value.inner_bytes())?;
 
-        // Parse dynamic event data.
-        let dynamic_event = parse_event(event_name, &event_data, event_schema)?;
+        parse_stored_event(
+            &self.fetcher.ces_metadata,
+            id,
+            event_value.inner_bytes(),
+            event_schema,
+        )
+    }
 
-        Ok(dynamic_event)
+    /// Fetches events with IDs from the given range, see `fetch_events_with_concurrency`.
+    pub async fn fetch_events(
+        &self,
+        range: Range<u32>,
+        event_schema: &Schemas,
+    ) -> Vec<(u32, Result<ContextualEvent, ToolkitError>)> {
+        self.fetch_events_with_concurrency(range, event_schema, DEFAULT_FETCH_CONCURRENCY)
+            .await
     }
 
-    pub async fn fetch_events_from_deploy(
+    /// Fetches events with IDs from the given range, running up to `max_concurrency` requests
+    /// at once. If the source supports batches, every request reads multiple events.
+    ///
+    /// Results are returned in event ID order, and failure of a single event does not abort
+    /// the whole batch.
+    pub async fn fetch_events_with_concurrency(
         &self,
-        deploy_hash: &str,
+        range: Range<u32>,
         event_schema: &Schemas,
-    ) -> Result<Vec<Event>, ToolkitError> {
-        // Build deploy hash.
-        let contract_hash_bytes = parse_hash(deploy_hash)?;
-        let deploy_hash = casper_client::types::DeployHash::new(contract_hash_bytes.into());
+        max_concurrency: usize,
+    ) -> Vec<(u32, Result<ContextualEvent, ToolkitError>)> {
+        let cached_events_count = self.cached_events_count(&range).await;
+        let max_batch_size = self.fetcher.client.max_batch_size();
+        if max_batch_size <= 1 {
+            return self
+                .fetch_single_events(range, event_schema, cached_events_count, max_concurrency)
+                .await;
+        }
 
-        let execution_result = self.client.get_deploy_result(deploy_hash).await?;
-        let effects = match execution_result {
-            casper_types::ExecutionResult::Failure { .. } => Err(ToolkitError::FailedDeployError),
-            casper_types::ExecutionResult::Success { effect, .. } => Ok(effect),
-        }?;
+        let ids: Vec<u32> = range.collect();
+        let batches: Vec<Vec<_>> = stream::iter(ids.chunks(max_batch_size))
+            .map(|ids| self.fetch_events_batch(ids, event_schema, cached_events_count))
+            .buffered(max_concurrency.max(1))
+            .collect()
+            .await;
+
+        // Events of rejected batches are read one by one.
+        let events: Vec<_> = batches.into_iter().flatten().collect();
+        let unread_ids: Vec<u32> = events
+            .iter()
+            .filter(|(_, event)| event.is_none())
+            .map(|(id, _)| *id)
+            .collect();
+        let mut unread_events = self
+            .fetch_single_events(
+                unread_ids,
+                event_schema,
+                cached_events_count,
+                max_concurrency,
+            )
+            .await
+            .into_iter();
+
+        events
+            .into_iter()
+            .map(|(id, event)| match event {
+                Some(event) => (id, event),
+                None => unread_events.next().expect("event of every unread id"),
+            })
+            .collect()
+    }
 
-        let mut events = vec![];
+    async fn fetch_single_events(
+        &self,
+        ids: impl IntoIterator<Item = u32>,
+        event_schema: &Schemas,
+        cached_events_count: u32,
+        max_concurrency: usize,
+    ) -> Vec<(u32, Result<ContextualEvent, ToolkitError>)> {
+        stream::iter(ids)
+            .map(|id| async move {
+                let event = match self.load_cached_event(id, cached_events_count) {
+                    Ok(Some(event_bytes)) => parse_stored_event(
+                        &self.fetcher.ces_metadata,
+                        id,
+                        &event_bytes,
+                        event_schema,
+                    ),
+                    Ok(None) => self.read_event(id, event_schema).await,
+                    Err(e) => Err(e),
+                };
+                (id, event)
+            })
+            .buffered(max_concurrency.max(1))
+            .collect()
+            .await
+    }
+
+    // Only events missing in the cache are read. Events of batch rejected by the node, e.g. not
+    // supporting batches, are left unread - as well as all the events once the source stopped
+    // sending batches. Other failures are shared by all the events.
+    async fn fetch_events_batch(
+        &self,
+        ids: &[u32],
+        event_schema: &Schemas,
+        cached_events_count: u32,
+    ) -> Vec<(u32, Option<Result<ContextualEvent, ToolkitError>>)> {
+        let mut events_bytes: Vec<_> = ids
+            .iter()
+            .map(|id| (*id, self.load_cached_event(*id, cached_events_count)))
+            .collect();
+        let missing_ids: Vec<u32> = events_bytes
+            .iter()
+            .filter(|(_, event_bytes)| matches!(event_bytes, Ok(None)))
+            .map(|(id, _)| *id)
+            .collect();
 
-        for entry in effects.transforms {
-            // Look for data writes into the global state.
-            let casper_types::Transform::WriteCLValue(clvalue) = entry.transform else {
-                continue;
+        let mut batch_rejected = self.fetcher.client.max_batch_size() <= 1;
+        if !missing_ids.is_empty() && !batch_rejected {
+            let events_data_uref = &self.fetcher.ces_metadata.events_data;
+            let item_keys: Vec<String> = missing_ids.iter().map(u32::to_string).collect();
+            let event_values = match self
+                .fetcher
+                .client
+                .get_stored_clvalues_from_dict(self.state_root_hash, events_data_uref, &item_keys)
+                .await
+            {
+                Ok(event_values) => event_values,
+                Err(e) if e.is_batch_rejection() => {
+                    batch_rejected = true;
+                    vec![]
+                }
+                Err(e) => {
+                    let error = Arc::new(e);
+                    missing_ids
+                        .iter()
+                        .map(|_id| Err(ToolkitError::BatchRequestFailed(error.clone())))
+                        .collect()
+                }
             };
 
-            // Look specifically for dictionaries writes.
-            const DICTIONARY_PREFIX: &str = "dictionary-";
-            if !entry.key.starts_with(DICTIONARY_PREFIX) {
-                continue;
+            let mut event_values = missing_ids.iter().zip(event_values);
+            for (id, event_bytes) in events_bytes.iter_mut() {
+                if !matches!(event_bytes, Ok(None)) {
+                    continue;
+                }
+                let Some((_, event_value)) = event_values.next() else {
+                    break;
+                };
+                *event_bytes = event_value.and_then(|event_value| {
+                    let bytes = event_value.inner_bytes();
+                    self.fetcher.save_cached_event(*id, bytes)?;
+                    Ok(Some(bytes.to_vec()))
+                });
             }
+        }
 
-            // Try parsing CES value, but ignore errors - we don't really know if this is CES dictionary,
-            // because write address is based on key (event ID).
-            let Ok((_total_length, event_value_bytes)) = u32::from_bytes(clvalue.inner_bytes())
-            else {
-                continue;
-            };
-            let Ok((event_name, event_data)) = parse_raw_event_name_and_data(event_value_bytes)
-            else {
-                continue;
-            };
+        events_bytes
+            .into_iter()
+            .map(|(id, event_bytes)| {
+                let event = match event_bytes {
+                    Ok(None) if batch_rejected => None,
+                    event_bytes => Some(event_bytes.and_then(|event_bytes| {
+                        let event_bytes =
+                            event_bytes.ok_or_else(|| ToolkitError::MissingStoredValue {
+                                context: format!("event {}", id),
+                            })?;
+                        parse_stored_event(
+                            &self.fetcher.ces_metadata,
+                            id,
+                            &event_bytes,
+                            event_schema,
+                        )
+                    })),
+                };
+                (id, event)
+            })
+            .collect()
+    }
 
-            // Parse dynamic event data.
-            let dynamic_event = parse_event(event_name, &event_data, event_schema)?;
+    // Number of events at the snapshot state, below which cached events can be used. Events
+    // count is not read if there is no cache.
+    async fn cached_events_count(&self, range: &Range<u32>) -> u32 {
+        if self.fetcher.event_cache.is_none() || range.is_empty() {
+            return 0;
+        }
+        self.fetch_events_count().await.unwrap_or(0)
+    }
 
-            events.push(dynamic_event);
+    fn load_cached_event(
+        &self,
+        id: u32,
+        cached_events_count: u32,
+    ) -> Result<Option<Vec<u8>>, ToolkitError> {
+        if id >= cached_events_count {
+            return Ok(None);
         }
+        self.fetcher.load_cached_event(id)
+    }
+}
 
-        Ok(events)
+#[cfg(test)]
+mod tests {
+    use casper_types::CLValue;
+
+    use super::*;
+    use crate::cache::MemoryEventCache;
+    use crate::event::Event;
+    use crate::source::MemorySource;
+    use crate::test_support::{test_event, test_schemas, HistorySource, MockNode};
+
+    fn source_with_events(events_count: u64) -> (MemorySource, CesMetadataRef) {
+        let mut source = MemorySource::new();
+        let ces_metadata = source.add_ces_contract([1; 32], &test_schemas()).unwrap();
+        for value in 0..events_count {
+            source
+                .push_event(&ces_metadata, &test_event(value))
+                .unwrap();
+        }
+        (source, ces_metadata)
+    }
+
+    fn event_value(event: &Result<ContextualEvent, ToolkitError>) -> Option<u64> {
+        let event = event.as_ref().ok()?;
+        event.event.fields[0].1.clone().into_t().ok()
+    }
+
+    #[tokio::test]
+    async fn test_fetch_events_range() {
+        let (source, ces_metadata) = source_with_events(5);
+        let fetcher = Fetcher::new(source, ces_metadata);
+        let schemas = test_schemas();
+
+        // Results keep ID order, events past the end fail on their own.
+        let events = fetcher
+            .fetch_events_with_concurrency(2..7, &schemas, 2)
+            .await
+            .unwrap();
+        let values: Vec<(u32, Option<u64>)> = events
+            .iter()
+            .map(|(id, event)| (*id, event_value(event)))
+            .collect();
+        assert_eq!(
+            values,
+            vec![
+                (2, Some(2)),
+                (3, Some(3)),
+                (4, Some(4)),
+                (5, None),
+                (6, None)
+            ]
+        );
+
+        // Zero concurrency still makes progress.
+        let events = fetcher
+            .fetch_events_with_concurrency(0..2, &schemas, 0)
+            .await
+            .unwrap();
+        assert_eq!(events.len(), 2);
+        assert!(fetcher
+            .fetch_events(3..3, &schemas)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_reads_are_pinned() {
+        // Second block adds an event, and a new event type to the schema.
+        let mut upgraded_schemas = test_schemas();
+        upgraded_schemas
+            .0
+            .insert("Other".to_string(), Default::default());
+        let mut states = vec![];
+        for (events_count, schemas) in [(1, test_schemas()), (2, upgraded_schemas)] {
+            let (mut source, ces_metadata) = source_with_events(events_count);
+            source.put_clvalue(
+                &ces_metadata.events_schema,
+                CLValue::from_t(schemas).unwrap(),
+            );
+            states.push(source);
+        }
+        let (_source, ces_metadata) = source_with_events(0);
+        let fetcher = Fetcher::new(HistorySource::new(states), ces_metadata);
+
+        let old_snapshot = fetcher.at_block_height(0).await.unwrap();
+        let latest_snapshot = fetcher.at_latest().await.unwrap();
+        assert_eq!(
+            old_snapshot.state_root_hash(),
+            HistorySource::state_root_hash(0)
+        );
+        assert_eq!(
+            latest_snapshot.state_root_hash(),
+            HistorySource::state_root_hash(1)
+        );
+
+        assert_eq!(old_snapshot.fetch_events_count().await.unwrap(), 1);
+        assert_eq!(old_snapshot.fetch_schema().await.unwrap().0.len(), 1);
+        let events = old_snapshot.fetch_events(0..2, &test_schemas()).await;
+        assert_eq!(event_value(&events[0].1), Some(0));
+        assert!(events[1].1.is_err());
+
+        assert_eq!(latest_snapshot.fetch_events_count().await.unwrap(), 2);
+        assert_eq!(latest_snapshot.fetch_schema().await.unwrap().0.len(), 2);
+        let event = latest_snapshot.fetch_event(1, &test_schemas()).await;
+        assert_eq!(event_value(&event), Some(1));
+    }
+
+    #[tokio::test]
+    async fn test_stream_events_reads_new_schema() {
+        let (source, ces_metadata) = source_with_events(1);
+        let node = MockNode::start(source).await.unwrap();
+        let client = CasperClient::new(&node.rpc_endpoint());
+        let fetcher = Fetcher::new(client, ces_metadata.clone());
+        let events = fetcher.stream_events(0, Duration::from_millis(10));
+        futures::pin_mut!(events);
+        assert_eq!(event_value(&events.next().await.unwrap()), Some(0));
+
+        // Contract adds a new event type.
+        let other_event = Event {
+            name: "Other".to_string(),
+            fields: vec![],
+        };
+        let mut upgraded_schemas = test_schemas();
+        upgraded_schemas
+            .0
+            .insert("Other".to_string(), Default::default());
+        {
+            let mut source = node.source_mut().await;
+            source.put_clvalue(
+                &ces_metadata.events_schema,
+                CLValue::from_t(upgraded_schemas).unwrap(),
+            );
+            source.push_event(&ces_metadata, &other_event).unwrap();
+        }
+        let event = events.next().await.unwrap().unwrap();
+        assert_eq!(event.event.name, "Other");
+
+        // Event missing in the schema ends the stream.
+        let unknown_event = Event {
+            name: "Unknown".to_string(),
+            fields: vec![],
+        };
+        node.source_mut()
+            .await
+            .push_event(&ces_metadata, &unknown_event)
+            .unwrap();
+        let error = events.next().await.unwrap().unwrap_err();
+        assert!(matches!(error, ToolkitError::MissingEventSchema(_)));
+        assert!(events.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_event_cache_saves_requests() {
+        let (source, ces_metadata) = source_with_events(4);
+        let node = MockNode::start(source).await.unwrap();
+        let schemas = test_schemas();
+
+        let client = CasperClient::new(&node.rpc_endpoint());
+        let fetcher =
+            Fetcher::new(client, ces_metadata).with_event_cache(Arc::new(MemoryEventCache::new()));
+        let snapshot = fetcher.at_state_root(Digest::default());
+
+        // Cold cache reads events count and every event.
+        let events = snapshot.fetch_events(0..4, &schemas).await;
+        assert!(events.iter().all(|(_, event)| event.is_ok()));
+        assert_eq!(node.request_count(), 5);
+
+        // Warm cache reads only events count.
+        let events = snapshot.fetch_events(0..4, &schemas).await;
+        let values: Vec<Option<u64>> = events.iter().map(|(_, event)| event_value(event)).collect();
+        assert_eq!(values, vec![Some(0), Some(1), Some(2), Some(3)]);
+        assert_eq!(node.request_count(), 6);
+    }
+
+    #[tokio::test]
+    async fn test_event_cache_batch_reads_missing_events() {
+        let (source, ces_metadata) = source_with_events(4);
+        let node = MockNode::start(source).await.unwrap();
+        let schemas = test_schemas();
+
+        let client = CasperClient::new(&node.rpc_endpoint()).with_batch_requests(10);
+        let fetcher =
+            Fetcher::new(client, ces_metadata).with_event_cache(Arc::new(MemoryEventCache::new()));
+        let snapshot = fetcher.at_state_root(Digest::default());
+        snapshot.fetch_event(1, &schemas).await.unwrap();
+        snapshot.fetch_event(3, &schemas).await.unwrap();
+        let requests_before = node.request_count();
+
+        // Events count and a single batch of events 0, 2 and 4.
+        let events = snapshot.fetch_events(0..5, &schemas).await;
+        let values: Vec<(u32, Option<u64>)> = events
+            .iter()
+            .map(|(id, event)| (*id, event_value(event)))
+            .collect();
+        assert_eq!(
+            values,
+            vec![
+                (0, Some(0)),
+                (1, Some(1)),
+                (2, Some(2)),
+                (3, Some(3)),
+                (4, None)
+            ]
+        );
+        assert_eq!(node.request_count() - requests_before, 2);
+    }
+
+    #[tokio::test]
+    async fn test_event_cache_respects_snapshot_state() {
+        let schemas = test_schemas();
+        let cache: Arc<dyn EventCache> = Arc::new(MemoryEventCache::new());
+        let (source, ces_metadata) = source_with_events(3);
+        let fetcher = Fetcher::new(source, ces_metadata).with_event_cache(cache.clone());
+        fetcher.fetch_event(2, &schemas).await.unwrap();
+
+        // Older state of the same contract, sharing the cache.
+        let (source, ces_metadata) = source_with_events(2);
+        let fetcher = Fetcher::new(source, ces_metadata).with_event_cache(cache);
+        let snapshot = fetcher.at_state_root(Digest::default());
+        assert!(snapshot.fetch_event(2, &schemas).await.is_err());
+        let events = snapshot.fetch_events(0..3, &schemas).await;
+        assert_eq!(event_value(&events[1].1), Some(1));
+        assert!(events[2].1.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_rejected_batch_reads_missing_events() {
+        let (source, ces_metadata) = source_with_events(5);
+        let node = MockNode::start(source).await.unwrap();
+        node.reject_batches(true);
+        let schemas = test_schemas();
+
+        let client = CasperClient::new(&node.rpc_endpoint()).with_batch_requests(2);
+        let fetcher =
+            Fetcher::new(client, ces_metadata).with_event_cache(Arc::new(MemoryEventCache::new()));
+        let snapshot = fetcher.at_state_root(Digest::default());
+        snapshot.fetch_event(1, &schemas).await.unwrap();
+        let requests_before = node.request_count();
+
+        // Events count, single rejected batch and every missing event.
+        let events = snapshot
+            .fetch_events_with_concurrency(0..5, &schemas, 1)
+            .await;
+        let values: Vec<Option<u64>> = events.iter().map(|(_, event)| event_value(event)).collect();
+        assert_eq!(values, vec![Some(0), Some(1), Some(2), Some(3), Some(4)]);
+        assert_eq!(node.request_count() - requests_before, 6);
+    }
+
+    #[tokio::test]
+    async fn test_batch_transport_error_is_shared() {
+        let (_source, ces_metadata) = source_with_events(0);
+        // Nothing listens on the port once the listener is dropped.
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let rpc_endpoint = format!("http://{}", listener.local_addr().unwrap());
+        drop(listener);
+
+        let client = CasperClient::new(&rpc_endpoint).with_batch_requests(10);
+        let fetcher = Fetcher::new(client, ces_metadata);
+        let events = fetcher
+            .at_state_root(Digest::default())
+            .fetch_events(0..3, &test_schemas())
+            .await;
+
+        assert_eq!(events.len(), 3);
+        for (_id, event) in events {
+            let error = event.unwrap_err();
+            assert!(matches!(error, ToolkitError::BatchRequestFailed(_)));
+            assert!(error.is_retryable());
+        }
     }
 }
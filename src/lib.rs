@@ -1,9 +1,17 @@
+pub mod cache;
 pub mod error;
 pub mod event;
 pub mod fetcher;
 pub mod metadata;
+pub mod package;
 pub mod parser;
+pub mod quorum;
 pub mod rpc;
+pub mod scanner;
+pub mod source;
+pub mod sse;
+#[cfg(any(test, feature = "test-support"))]
+pub mod test_support;
 pub(crate) mod utils;
 
 // Main types exposed by this library.
@@ -1,45 +1,414 @@
 use casper_event_standard::casper_types;
+use casper_hashing::Digest;
+use casper_types::account::AccountHash;
+use casper_types::contracts::NamedKeys;
+use casper_types::HashAddr;
 
 pub use casper_types::URef;
 
 use crate::error::ToolkitError;
-use crate::rpc::client::CasperClient;
+use crate::parser::CesVersion;
+use crate::rpc::types::ContractVersionInfo;
+use crate::source::GlobalStateSource;
 use crate::utils::{self, parse_hash};
 
-const EVENTS_SCHEMA_KEY: &str = "__events_schema";
-const EVENTS_LENGTH_KEY: &str = "__events_length";
-const EVENTS_DATA_KEY: &str = "__events";
+pub(crate) const EVENTS_SCHEMA_KEY: &str = "__events_schema";
+pub(crate) const EVENTS_LENGTH_KEY: &str = "__events_length";
+pub(crate) const EVENTS_DATA_KEY: &str = "__events";
+pub(crate) const EVENTS_CES_VERSION_KEY: &str = "__events_ces_version";
 
-#[derive(Debug)]
+const ACCOUNT_HASH_PREFIX: &str = "account-hash-";
+
+/// Names of the named keys holding CES metadata.
+///
+/// Default names are the ones used by the standard, contracts not following it can use others.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CesKeyNames {
+    pub events_schema: String,
+    pub events_length: String,
+    pub events_data: String,
+    /// Optional key, holding the CES version string.
+    pub ces_version: String,
+}
+
+impl Default for CesKeyNames {
+    fn default() -> Self {
+        Self {
+            events_schema: EVENTS_SCHEMA_KEY.to_string(),
+            events_length: EVENTS_LENGTH_KEY.to_string(),
+            events_data: EVENTS_DATA_KEY.to_string(),
+            ces_version: EVENTS_CES_VERSION_KEY.to_string(),
+        }
+    }
+}
+
+/// Version of the contract to resolve from the contract package.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PackageVersion {
+    /// Latest enabled version, changing with every contract upgrade.
+    #[default]
+    LatestEnabled,
+    /// Given version, even if already disabled.
+    Exact {
+        protocol_version_major: u32,
+        contract_version: u32,
+    },
+}
+
+#[derive(Clone, Debug)]
 pub struct CesMetadataRef {
+    /// Contract that owns the metadata, if known.
+    pub contract_hash: Option<HashAddr>,
     pub events_schema: URef,
     pub events_length: URef,
     pub events_data: URef,
+    /// Version of the standard the events are stored with, if the contract stores it.
+    pub ces_version: Option<String>,
 }
 
 impl CesMetadataRef {
     pub async fn fetch_metadata(
-        client: &CasperClient,
+        client: &impl GlobalStateSource,
+        contract_hash: &str,
+    ) -> Result<CesMetadataRef, ToolkitError> {
+        // Fetch latest state root hash.
+        let state_root_hash = client.get_state_root_hash(None).await?;
+
+        Self::fetch_metadata_at(client, state_root_hash, contract_hash).await
+    }
+
+    /// Fetches CES metadata of the contract resolved from the contract package.
+    pub async fn fetch_metadata_from_package(
+        client: &impl GlobalStateSource,
+        contract_package_hash: &str,
+        version: PackageVersion,
+    ) -> Result<CesMetadataRef, ToolkitError> {
+        let state_root_hash = client.get_state_root_hash(None).await?;
+
+        Self::fetch_metadata_from_package_at(
+            client,
+            state_root_hash,
+            contract_package_hash,
+            version,
+        )
+        .await
+    }
+
+    /// Fetches CES metadata of the contract resolved from the contract package, at given state
+    /// root hash.
+    pub async fn fetch_metadata_from_package_at(
+        client: &impl GlobalStateSource,
+        state_root_hash: Digest,
+        contract_package_hash: &str,
+        version: PackageVersion,
+    ) -> Result<CesMetadataRef, ToolkitError> {
+        let contract_package_hash = parse_hash(contract_package_hash)?;
+        let contract_package = client
+            .get_contract_package(state_root_hash, contract_package_hash)
+            .await?;
+
+        let contract_version = match version {
+            PackageVersion::LatestEnabled => contract_package.latest_enabled_version(),
+            PackageVersion::Exact {
+                protocol_version_major,
+                contract_version,
+            } => contract_package.find_version(protocol_version_major, contract_version),
+        };
+        let Some(ContractVersionInfo { contract_hash, .. }) = contract_version else {
+            return Err(ToolkitError::MissingContractVersion {
+                context: format!(
+                    "{:?} of package {}",
+                    version,
+                    hex::encode(contract_package_hash)
+                ),
+            });
+        };
+
+        Self::fetch_metadata_at(client, state_root_hash, &hex::encode(contract_hash)).await
+    }
+
+    /// Fetches CES metadata from the global state at given state root hash.
+    pub async fn fetch_metadata_at(
+        client: &impl GlobalStateSource,
+        state_root_hash: Digest,
+        contract_hash: &str,
+    ) -> Result<CesMetadataRef, ToolkitError> {
+        Self::fetch_metadata_with_key_names_at(
+            client,
+            state_root_hash,
+            contract_hash,
+            &CesKeyNames::default(),
+        )
+        .await
+    }
+
+    /// Fetches CES metadata stored by the contract under custom named keys.
+    pub async fn fetch_metadata_with_key_names(
+        client: &impl GlobalStateSource,
         contract_hash: &str,
+        key_names: &CesKeyNames,
+    ) -> Result<CesMetadataRef, ToolkitError> {
+        let state_root_hash = client.get_state_root_hash(None).await?;
+
+        Self::fetch_metadata_with_key_names_at(client, state_root_hash, contract_hash, key_names)
+            .await
+    }
+
+    /// Fetches CES metadata stored by the contract under custom named keys, at given state root
+    /// hash.
+    pub async fn fetch_metadata_with_key_names_at(
+        client: &impl GlobalStateSource,
+        state_root_hash: Digest,
+        contract_hash: &str,
+        key_names: &CesKeyNames,
     ) -> Result<CesMetadataRef, ToolkitError> {
         // Build contract hash.
         let contract_hash = parse_hash(contract_hash)?;
 
         // Fetch contract named keys.
-        let contract_named_keys = client.get_contract_named_keys(contract_hash).await?;
+        let contract_named_keys = client
+            .get_contract_named_keys(state_root_hash, contract_hash)
+            .await?;
+
+        let mut ces_metadata = Self::from_named_keys(&contract_named_keys, key_names)?;
+        ces_metadata.contract_hash = Some(contract_hash);
+        ces_metadata.ces_version =
+            fetch_ces_version(client, state_root_hash, &contract_named_keys, key_names).await?;
+
+        Ok(ces_metadata)
+    }
 
-        // Extract CES metadata from named keys.
+    /// Fetches CES metadata stored under the account named keys.
+    ///
+    /// Account hash is hex encoded, with optional `account-hash-` prefix.
+    pub async fn fetch_account_metadata(
+        client: &impl GlobalStateSource,
+        account_hash: &str,
+        key_names: &CesKeyNames,
+    ) -> Result<CesMetadataRef, ToolkitError> {
+        let state_root_hash = client.get_state_root_hash(None).await?;
+
+        Self::fetch_account_metadata_at(client, state_root_hash, account_hash, key_names).await
+    }
+
+    /// Fetches CES metadata stored under the account named keys, at given state root hash.
+    pub async fn fetch_account_metadata_at(
+        client: &impl GlobalStateSource,
+        state_root_hash: Digest,
+        account_hash: &str,
+        key_names: &CesKeyNames,
+    ) -> Result<CesMetadataRef, ToolkitError> {
+        let account_hash = account_hash
+            .strip_prefix(ACCOUNT_HASH_PREFIX)
+            .unwrap_or(account_hash);
+        let account_hash = AccountHash::new(parse_hash(account_hash)?);
+
+        let account_named_keys = client
+            .get_account_named_keys(state_root_hash, account_hash)
+            .await?;
+
+        let mut ces_metadata = Self::from_named_keys(&account_named_keys, key_names)?;
+        ces_metadata.ces_version =
+            fetch_ces_version(client, state_root_hash, &account_named_keys, key_names).await?;
+
+        Ok(ces_metadata)
+    }
+
+    /// Extracts CES metadata from already known named keys. Owning contract and CES version
+    /// are not set.
+    pub fn from_named_keys(
+        named_keys: &NamedKeys,
+        key_names: &CesKeyNames,
+    ) -> Result<CesMetadataRef, ToolkitError> {
         let events_schema_uref =
-            utils::extract_uref_from_named_keys(&contract_named_keys, EVENTS_SCHEMA_KEY)?;
+            utils::extract_uref_from_named_keys(named_keys, &key_names.events_schema)?;
         let events_length_uref =
-            utils::extract_uref_from_named_keys(&contract_named_keys, EVENTS_LENGTH_KEY)?;
+            utils::extract_uref_from_named_keys(named_keys, &key_names.events_length)?;
         let events_data_uref =
-            utils::extract_uref_from_named_keys(&contract_named_keys, EVENTS_DATA_KEY)?;
+            utils::extract_uref_from_named_keys(named_keys, &key_names.events_data)?;
 
         Ok(CesMetadataRef {
+            contract_hash: None,
             events_data: events_data_uref,
             events_length: events_length_uref,
             events_schema: events_schema_uref,
+            ces_version: None,
         })
     }
+
+    /// Builds CES metadata from explicitly given URefs, e.g. stored outside of named keys.
+    pub fn from_urefs(events_schema: URef, events_length: URef, events_data: URef) -> Self {
+        CesMetadataRef {
+            contract_hash: None,
+            events_schema,
+            events_length,
+            events_data,
+            ces_version: None,
+        }
+    }
+}
+
+// Version key is optional, contracts may predate it. Unsupported version is rejected right
+// away, as reading the schema stored with it would fail with unclear error.
+async fn fetch_ces_version(
+    client: &impl GlobalStateSource,
+    state_root_hash: Digest,
+    named_keys: &NamedKeys,
+    key_names: &CesKeyNames,
+) -> Result<Option<String>, ToolkitError> {
+    if !named_keys.contains_key(&key_names.ces_version) {
+        return Ok(None);
+    }
+    let ces_version_uref = utils::extract_uref_from_named_keys(named_keys, &key_names.ces_version)?;
+    let ces_version: String = client
+        .get_stored_clvalue(state_root_hash, &ces_version_uref)
+        .await?
+        .into_t()
+        .map_err(|e| ToolkitError::InvalidCLValue(e.to_string()))?;
+    CesVersion::from_stored(Some(&ces_version))?;
+
+    Ok(Some(ces_version))
+}
+
+#[cfg(test)]
+mod tests {
+    use casper_event_standard::Schemas;
+    use casper_types::{AccessRights, CLValue};
+
+    use super::*;
+    use crate::rpc::client::CasperClient;
+    use crate::rpc::types::ContractPackageInfo;
+    use crate::source::MemorySource;
+    use crate::test_support::MockNode;
+
+    #[tokio::test]
+    async fn test_fetch_metadata_from_package() {
+        let mut source = MemorySource::new();
+        let first_metadata = source.add_ces_contract([1; 32], &Schemas::new()).unwrap();
+        let second_metadata = source.add_ces_contract([2; 32], &Schemas::new()).unwrap();
+        let versions = [([1; 32], 1, true), ([2; 32], 2, true), ([3; 32], 3, false)]
+            .into_iter()
+            .map(
+                |(contract_hash, contract_version, enabled)| ContractVersionInfo {
+                    protocol_version_major: 1,
+                    contract_version,
+                    contract_hash,
+                    enabled,
+                },
+            )
+            .collect();
+        let contract_package_hash = [9; 32];
+        source.put_contract_package(contract_package_hash, ContractPackageInfo { versions });
+
+        let node = MockNode::start(source).await.unwrap();
+        let client = CasperClient::new(&node.rpc_endpoint());
+        let contract_package_hash = hex::encode(contract_package_hash);
+
+        let metadata = CesMetadataRef::fetch_metadata_from_package(
+            &client,
+            &contract_package_hash,
+            PackageVersion::LatestEnabled,
+        )
+        .await
+        .unwrap();
+        assert_eq!(metadata.contract_hash, Some([2; 32]));
+        assert_eq!(metadata.events_data, second_metadata.events_data);
+
+        let version = PackageVersion::Exact {
+            protocol_version_major: 1,
+            contract_version: 1,
+        };
+        let metadata =
+            CesMetadataRef::fetch_metadata_from_package(&client, &contract_package_hash, version)
+                .await
+                .unwrap();
+        assert_eq!(metadata.events_data, first_metadata.events_data);
+
+        let version = PackageVersion::Exact {
+            protocol_version_major: 2,
+            contract_version: 1,
+        };
+        let result =
+            CesMetadataRef::fetch_metadata_from_package(&client, &contract_package_hash, version)
+                .await;
+        assert!(matches!(
+            result,
+            Err(ToolkitError::MissingContractVersion { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_metadata_unsupported_ces_version() {
+        let mut source = MemorySource::new();
+        let ces_metadata = source.add_ces_contract([1; 32], &Schemas::new()).unwrap();
+        let ces_version_uref = URef::new([9; 32], AccessRights::READ_ADD_WRITE);
+        let mut named_keys = NamedKeys::new();
+        for (name, uref) in [
+            (EVENTS_SCHEMA_KEY, ces_metadata.events_schema),
+            (EVENTS_LENGTH_KEY, ces_metadata.events_length),
+            (EVENTS_DATA_KEY, ces_metadata.events_data),
+            (EVENTS_CES_VERSION_KEY, ces_version_uref),
+        ] {
+            named_keys.insert(name.to_string(), casper_types::Key::URef(uref));
+        }
+        source.put_named_keys([2; 32], named_keys);
+        source.put_clvalue(
+            &ces_version_uref,
+            CLValue::from_t("2.0".to_string()).unwrap(),
+        );
+
+        let result = CesMetadataRef::fetch_metadata(&source, &hex::encode([2; 32])).await;
+        assert!(matches!(
+            result,
+            Err(ToolkitError::UnsupportedCesVersion(version)) if version == "2.0"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_account_metadata_with_key_names() {
+        let mut source = MemorySource::new();
+        let ces_metadata = source.add_ces_contract([1; 32], &Schemas::new()).unwrap();
+        let key_names = CesKeyNames {
+            events_schema: "schema".to_string(),
+            events_length: "length".to_string(),
+            events_data: "events".to_string(),
+            ces_version: "version".to_string(),
+        };
+        let mut named_keys = NamedKeys::new();
+        for (name, uref) in [
+            (&key_names.events_schema, ces_metadata.events_schema),
+            (&key_names.events_length, ces_metadata.events_length),
+            (&key_names.events_data, ces_metadata.events_data),
+        ] {
+            named_keys.insert(name.clone(), casper_types::Key::URef(uref));
+        }
+        let account_hash = AccountHash::new([5; 32]);
+        source.put_account_named_keys(account_hash, named_keys);
+
+        let node = MockNode::start(source).await.unwrap();
+        let client = CasperClient::new(&node.rpc_endpoint());
+
+        let metadata = CesMetadataRef::fetch_account_metadata(
+            &client,
+            &account_hash.to_formatted_string(),
+            &key_names,
+        )
+        .await
+        .unwrap();
+        assert_eq!(metadata.contract_hash, None);
+        assert_eq!(metadata.events_schema, ces_metadata.events_schema);
+        assert_eq!(metadata.events_data, ces_metadata.events_data);
+
+        // Standard names are not present.
+        let result = CesMetadataRef::fetch_account_metadata(
+            &client,
+            &hex::encode(account_hash.value()),
+            &CesKeyNames::default(),
+        )
+        .await;
+        assert!(matches!(
+            result,
+            Err(ToolkitError::MissingMetadataKey { .. })
+        ));
+    }
 }
@@ -0,0 +1,386 @@
+use std::ops::Range;
+use std::sync::Arc;
+
+use casper_client::rpcs::common::BlockIdentifier;
+use casper_event_standard::casper_types::HashAddr;
+use casper_hashing::Digest;
+
+use crate::cache::EventCache;
+use crate::error::ToolkitError;
+use crate::event::ContextualEvent;
+use crate::fetcher::Fetcher;
+use crate::metadata::CesMetadataRef;
+use crate::rpc::client::CasperClient;
+use crate::rpc::types::ContractVersionInfo;
+use crate::source::GlobalStateSource;
+use crate::utils::parse_hash;
+
+/// Fetches events of all the contract versions in the package, as one continuous history.
+///
+/// Contract upgrade may keep the CES storage of the previous version, or create a new one.
+/// Versions sharing the storage are grouped, so every event is read once. History consists of
+/// the events of every storage in turn, starting from the storage of the oldest version.
+pub struct PackageFetcher<S = CasperClient> {
+    pub client: S,
+    pub contract_package_hash: HashAddr,
+    pub storages: Vec<PackageStorage>,
+    event_cache: Option<Arc<dyn EventCache>>,
+}
+
+/// CES storage written by one or more contract versions.
+#[derive(Debug)]
+pub struct PackageStorage {
+    pub ces_metadata: CesMetadataRef,
+    /// Versions writing to the storage, ordered from the oldest.
+    pub versions: Vec<StorageVersion>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StorageVersion {
+    pub version: ContractVersionInfo,
+    /// ID of the first event emitted by the version.
+    pub first_event_id: u32,
+}
+
+/// Event of the package, with the contract version that emitted it.
+#[derive(Debug)]
+pub struct PackageEvent {
+    /// Position in the package events history.
+    pub position: u64,
+    pub version: ContractVersionInfo,
+    pub event: ContextualEvent,
+}
+
+impl PackageStorage {
+    /// Returns the version that emitted the event with given ID.
+    pub fn emitting_version(&self, id: u32) -> Option<&ContractVersionInfo> {
+        self.versions
+            .iter()
+            .rev()
+            .find(|version| version.first_event_id <= id)
+            .map(|version| &version.version)
+    }
+
+    // Attributes the event to the version that emitted it.
+    fn package_event(
+        &self,
+        position: u64,
+        mut event: ContextualEvent,
+    ) -> Result<PackageEvent, ToolkitError> {
+        let version = self
+            .emitting_version(event.context.event_id)
+            .cloned()
+            .ok_or_else(|| ToolkitError::UnexpectedError {
+                context: "storage without versions".into(),
+            })?;
+        event.context.contract_hash = Some(version.contract_hash);
+
+        Ok(PackageEvent {
+            position,
+            version,
+            event,
+        })
+    }
+}
+
+impl<S: GlobalStateSource> PackageFetcher<S> {
+    /// Discovers CES storages of all the package versions, at the latest block.
+    ///
+    /// Versions without CES metadata, e.g. deployed before adopting the standard, are skipped.
+    /// When the version shares the storage with the older one, blocks history is searched for
+    /// the upgrade, to tell which version emitted which events. Only the blocks available on
+    /// the node are searched - `ToolkitError::MissingBlock` is returned if the upgrade happened
+    /// before them.
+    pub async fn fetch_package(
+        client: S,
+        contract_package_hash: &str,
+    ) -> Result<Self, ToolkitError> {
+        let contract_package_hash = parse_hash(contract_package_hash)?;
+        let latest_block = client.get_latest_block().await?;
+        let state_root_hash = latest_block.state_root_hash;
+        let contract_package = client
+            .get_contract_package(state_root_hash, contract_package_hash)
+            .await?;
+
+        let mut storages: Vec<PackageStorage> = vec![];
+        for version in contract_package.versions {
+            let contract_hash = hex::encode(version.contract_hash);
+            let ces_metadata =
+                match CesMetadataRef::fetch_metadata_at(&client, state_root_hash, &contract_hash)
+                    .await
+                {
+                    Ok(ces_metadata) => ces_metadata,
+                    Err(ToolkitError::MissingMetadataKey { .. }) => continue,
+                    Err(e) => return Err(e),
+                };
+
+            let events_data = ces_metadata.events_data.addr();
+            match storages
+                .iter_mut()
+                .find(|storage| storage.ces_metadata.events_data.addr() == events_data)
+            {
+                Some(storage) => {
+                    let first_event_id = find_first_event_id(
+                        &client,
+                        contract_package_hash,
+                        &storage.ces_metadata,
+                        &version,
+                        latest_block.height,
+                    )
+                    .await?;
+                    storage.versions.push(StorageVersion {
+                        version,
+                        first_event_id,
+                    });
+                }
+                None => storages.push(PackageStorage {
+                    ces_metadata,
+                    versions: vec![StorageVersion {
+                        version,
+                        first_event_id: 0,
+                    }],
+                }),
+            }
+        }
+
+        Ok(Self {
+            client,
+            contract_package_hash,
+            storages,
+            event_cache: None,
+        })
+    }
+
+    /// Consults the given cache before reading events and schemas of every storage, see
+    /// `Fetcher::with_event_cache`.
+    pub fn with_event_cache(mut self, event_cache: Arc<dyn EventCache>) -> Self {
+        self.event_cache = Some(event_cache);
+        self
+    }
+
+    /// Counts events of all the storages.
+    pub async fn fetch_events_count(&self) -> Result<u64, ToolkitError> {
+        let state_root_hash = self.client.get_state_root_hash(None).await?;
+        let events_counts = self.fetch_events_counts(state_root_hash).await?;
+
+        Ok(events_counts.iter().map(|count| u64::from(*count)).sum())
+    }
+
+    /// Fetches events at given positions of the history, with schemas stored by the contract.
+    ///
+    /// All the reads are pinned to the latest state root hash. Results are returned in
+    /// position order, positions past the end of the history are skipped.
+    pub async fn fetch_events(
+        &self,
+        range: Range<u64>,
+    ) -> Result<Vec<(u64, Result<PackageEvent, ToolkitError>)>, ToolkitError> {
+        let state_root_hash = self.client.get_state_root_hash(None).await?;
+        let events_counts = self.fetch_events_counts(state_root_hash).await?;
+
+        // Every storage holds a continuous part of the history.
+        let mut events = vec![];
+        let mut offset = 0;
+        for (storage, events_count) in self.storages.iter().zip(events_counts) {
+            let storage_end = offset + u64::from(events_count);
+            let start = range.start.clamp(offset, storage_end);
+            let end = range.end.clamp(start, storage_end);
+            if start < end {
+                let fetcher = self.storage_fetcher(storage);
+                let snapshot = fetcher.at_latest_state_root(state_root_hash);
+                let events_schema = snapshot.fetch_schema().await?;
+                let ids = (start - offset) as u32..(end - offset) as u32;
+                for (id, event) in snapshot.fetch_events(ids, &events_schema).await {
+                    let position = offset + u64::from(id);
+                    let event = event.and_then(|event| storage.package_event(position, event));
+                    events.push((position, event));
+                }
+            }
+            offset = storage_end;
+        }
+
+        Ok(events)
+    }
+
+    async fn fetch_events_counts(&self, state_root_hash: Digest) -> Result<Vec<u32>, ToolkitError> {
+        let mut events_counts = vec![];
+        for storage in &self.storages {
+            let events_count = self
+                .storage_fetcher(storage)
+                .at_latest_state_root(state_root_hash)
+                .fetch_events_count()
+                .await?;
+            events_counts.push(events_count);
+        }
+
+        Ok(events_counts)
+    }
+
+    // Fetcher of the storage events, sharing the client and the cache.
+    fn storage_fetcher(&self, storage: &PackageStorage) -> Fetcher<&S> {
+        let fetcher = Fetcher::new(&self.client, storage.ces_metadata.clone());
+        match &self.event_cache {
+            Some(event_cache) => fetcher.with_event_cache(event_cache.clone()),
+            None => fetcher,
+        }
+    }
+}
+
+// Finds the first block with the version in the package, returning events count from before
+// that block. Events emitted in the upgrade block itself are attributed to the new version.
+//
+// Only the blocks available on the node are searched. Version present already in the lowest
+// one was added before the available history, so the upgrade block can not be found.
+async fn find_first_event_id(
+    client: &impl GlobalStateSource,
+    contract_package_hash: HashAddr,
+    ces_metadata: &CesMetadataRef,
+    version: &ContractVersionInfo,
+    latest_height: u64,
+) -> Result<u32, ToolkitError> {
+    let lowest_height = client.get_lowest_available_block_height().await?;
+    let (mut low, mut high) = (lowest_height, latest_height);
+    while low < high {
+        let middle = low + (high - low) / 2;
+        let state_root_hash = client
+            .get_state_root_hash(Some(BlockIdentifier::Height(middle)))
+            .await?;
+        // Package missing in the older global state is reported as a missing value.
+        let has_version = match client
+            .get_contract_package(state_root_hash, contract_package_hash)
+            .await
+        {
+            Ok(contract_package) => contract_package
+                .find_version(version.protocol_version_major, version.contract_version)
+                .is_some(),
+            Err(e) if e.is_missing_value() => false,
+            Err(e) => return Err(e),
+        };
+        match has_version {
+            true => high = middle,
+            false => low = middle + 1,
+        }
+    }
+    if low == 0 {
+        return Ok(0);
+    }
+    if low == lowest_height {
+        return Err(ToolkitError::MissingBlock);
+    }
+
+    let state_root_hash = client
+        .get_state_root_hash(Some(BlockIdentifier::Height(low - 1)))
+        .await?;
+    Fetcher::new(client, ces_metadata.clone())
+        .at_state_root(state_root_hash)
+        .fetch_events_count()
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use casper_event_standard::casper_types::contracts::NamedKeys;
+    use casper_event_standard::casper_types::Key;
+
+    use super::*;
+    use crate::metadata::{EVENTS_DATA_KEY, EVENTS_LENGTH_KEY, EVENTS_SCHEMA_KEY};
+    use crate::rpc::types::ContractPackageInfo;
+    use crate::source::MemorySource;
+    use crate::test_support::{test_event, test_schemas, HistorySource};
+
+    const PACKAGE_HASH: HashAddr = [9; 32];
+
+    fn version(contract_hash: HashAddr, contract_version: u32) -> ContractVersionInfo {
+        ContractVersionInfo {
+            protocol_version_major: 1,
+            contract_version,
+            contract_hash,
+            enabled: true,
+        }
+    }
+
+    // Version 1 has no CES storage, version 2 creates one, version 3 is added at height 2 and
+    // keeps it, version 4 is added at height 3 with a new storage.
+    fn package_state(height: u8) -> MemorySource {
+        let mut source = MemorySource::new();
+        source.put_named_keys([1; 32], NamedKeys::new());
+        let mut versions = vec![version([1; 32], 1), version([2; 32], 2)];
+
+        let shared_metadata = source.add_ces_contract([2; 32], &test_schemas()).unwrap();
+        for value in 0..[2, 3, 3, 4][height as usize] {
+            source
+                .push_event(&shared_metadata, &test_event(value))
+                .unwrap();
+        }
+        if height >= 2 {
+            let mut named_keys = NamedKeys::new();
+            for (name, uref) in [
+                (EVENTS_SCHEMA_KEY, shared_metadata.events_schema),
+                (EVENTS_LENGTH_KEY, shared_metadata.events_length),
+                (EVENTS_DATA_KEY, shared_metadata.events_data),
+            ] {
+                named_keys.insert(name.to_string(), Key::URef(uref));
+            }
+            source.put_named_keys([3; 32], named_keys);
+            versions.push(version([3; 32], 3));
+        }
+        if height >= 3 {
+            let own_metadata = source.add_ces_contract([4; 32], &test_schemas()).unwrap();
+            source.push_event(&own_metadata, &test_event(100)).unwrap();
+            versions.push(version([4; 32], 4));
+        }
+        source.put_contract_package(PACKAGE_HASH, ContractPackageInfo { versions });
+
+        source
+    }
+
+    #[tokio::test]
+    async fn test_package_history() {
+        let client = HistorySource::new((0..4).map(package_state).collect());
+        let fetcher = PackageFetcher::fetch_package(client, &hex::encode(PACKAGE_HASH))
+            .await
+            .unwrap();
+
+        assert_eq!(fetcher.storages.len(), 2);
+        let first_event_ids: Vec<u32> = fetcher.storages[0]
+            .versions
+            .iter()
+            .map(|version| version.first_event_id)
+            .collect();
+        assert_eq!(first_event_ids, vec![0, 3]);
+        assert_eq!(fetcher.fetch_events_count().await.unwrap(), 5);
+
+        let events: Vec<(u64, u32, u64)> = fetcher
+            .fetch_events(1..10)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|(position, event)| {
+                let event = event.unwrap();
+                let value = event.event.event.fields[0].1.clone().into_t().unwrap();
+                (position, event.version.contract_version, value)
+            })
+            .collect();
+        assert_eq!(events, vec![(1, 2, 1), (2, 2, 2), (3, 3, 3), (4, 4, 100)]);
+    }
+
+    #[tokio::test]
+    async fn test_package_history_from_lowest_available_block() {
+        // Version 3 is added at height 4, blocks below 3 are not available.
+        let states = || [0, 0, 0, 1, 2, 3].into_iter().map(package_state).collect();
+        let client = HistorySource::new(states()).with_lowest_available_height(3);
+        let fetcher = PackageFetcher::fetch_package(client, &hex::encode(PACKAGE_HASH))
+            .await
+            .unwrap();
+        let first_event_ids: Vec<u32> = fetcher.storages[0]
+            .versions
+            .iter()
+            .map(|version| version.first_event_id)
+            .collect();
+        assert_eq!(first_event_ids, vec![0, 3]);
+
+        // Upgrade happened before the available blocks.
+        let client = HistorySource::new(states()).with_lowest_available_height(5);
+        let result = PackageFetcher::fetch_package(client, &hex::encode(PACKAGE_HASH)).await;
+        assert!(matches!(result, Err(ToolkitError::MissingBlock)));
+    }
+}
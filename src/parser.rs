@@ -1,9 +1,42 @@
+use std::collections::BTreeMap;
+
 use casper_event_standard::casper_types;
 use casper_event_standard::Schemas;
 use casper_types::bytesrepr::FromBytes;
+use casper_types::{Key, Transform, TransformEntry};
 
 use crate::error::ToolkitError;
-use crate::event::Event;
+use crate::event::{ContextualEvent, Event, EventContext};
+use crate::metadata::CesMetadataRef;
+
+/// Version of the standard, determining the events wire format.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CesVersion {
+    /// CES 1.x - event name with `event_` prefix, followed by the event fields.
+    V1,
+}
+
+impl CesVersion {
+    /// Parses version stored by the contract. Contracts without stored version use the first
+    /// format.
+    pub fn from_stored(ces_version: Option<&str>) -> Result<Self, ToolkitError> {
+        match ces_version {
+            None => Ok(CesVersion::V1),
+            Some(version) if version == "1" || version.starts_with("1.") => Ok(CesVersion::V1),
+            Some(version) => Err(ToolkitError::UnsupportedCesVersion(version.to_string())),
+        }
+    }
+}
+
+/// Parses event bytes with the rules of the CES version of the contract.
+pub fn parse_versioned_event_name_and_data(
+    ces_metadata: &CesMetadataRef,
+    bytes: &[u8],
+) -> Result<(String, Vec<u8>), ToolkitError> {
+    match CesVersion::from_stored(ces_metadata.ces_version.as_deref())? {
+        CesVersion::V1 => parse_raw_event_name_and_data(bytes),
+    }
+}
 
 pub fn parse_raw_event_name_and_data(bytes: &[u8]) -> Result<(String, Vec<u8>), ToolkitError> {
     let (_total_length, event_data_with_name) =
@@ -41,3 +74,164 @@ pub fn parse_event(
 
     Ok(dynamic_event)
 }
+
+// Parse contract events written into the dictionary by deploy execution.
+//
+// Events emitted by a deploy always have the latest IDs, so they are found by going backwards
+// from the events count written into `__events_length`, and checking if there was a write
+// under dictionary address derived from the event ID.
+pub fn parse_events_from_transforms(
+    transforms: &[TransformEntry],
+    ces_metadata: &CesMetadataRef,
+    schemas: &Schemas,
+) -> Result<Vec<ContextualEvent>, ToolkitError> {
+    let mut events_length = None;
+    let mut dictionary_writes = BTreeMap::new();
+
+    for (transform_index, entry) in transforms.iter().enumerate() {
+        // Look for data writes into the global state.
+        let Transform::WriteCLValue(clvalue) = &entry.transform else {
+            continue;
+        };
+
+        match Key::from_formatted_str(&entry.key) {
+            Ok(Key::URef(uref)) if uref.addr() == ces_metadata.events_length.addr() => {
+                let length: u32 = clvalue
+                    .clone()
+                    .into_t()
+                    .map_err(|e| ToolkitError::InvalidCLValue(e.to_string()))?;
+                events_length = events_length.max(Some(length));
+            }
+            Ok(Key::Dictionary(dictionary_addr)) => {
+                dictionary_writes.insert(dictionary_addr, (transform_index, clvalue));
+            }
+            _ => {}
+        }
+    }
+
+    // No events emitted by this contract.
+    let Some(events_length) = events_length else {
+        return Ok(vec![]);
+    };
+
+    let mut events = vec![];
+
+    for id in (0..events_length).rev() {
+        let event_key = Key::dictionary(ces_metadata.events_data, id.to_string().as_bytes());
+        let Some((transform_index, clvalue)) = event_key
+            .as_dictionary()
+            .and_then(|dictionary_addr| dictionary_writes.get(dictionary_addr))
+        else {
+            break;
+        };
+
+        // Dictionary value wraps the stored CLValue.
+        let (_total_length, event_value_bytes) =
+            u32::from_bytes(clvalue.inner_bytes()).map_err(|_e| {
+                ToolkitError::DeserializationError {
+                    context: "dictionary value",
+                }
+            })?;
+        let (event_name, event_data) =
+            parse_versioned_event_name_and_data(ces_metadata, event_value_bytes)?;
+
+        // Parse dynamic event data.
+        let dynamic_event = parse_event(event_name, &event_data, schemas)?;
+
+        let context = EventContext {
+            contract_hash: ces_metadata.contract_hash,
+            event_id: id,
+            transform_index: Some(*transform_index as u32),
+            ..Default::default()
+        };
+
+        events.push(ContextualEvent {
+            context,
+            event: dynamic_event,
+        });
+    }
+
+    // Restore emission order.
+    events.reverse();
+
+    Ok(events)
+}
+
+#[cfg(test)]
+mod tests {
+    use casper_types::{AccessRights, URef};
+
+    use super::*;
+    use crate::test_support::{event_transform, events_length_transform, test_event, test_schemas};
+
+    fn metadata(seed: u8) -> CesMetadataRef {
+        CesMetadataRef {
+            contract_hash: Some([seed; 32]),
+            events_schema: URef::new([seed; 32], AccessRights::READ_ADD_WRITE),
+            events_length: URef::new([seed + 1; 32], AccessRights::READ_ADD_WRITE),
+            events_data: URef::new([seed + 2; 32], AccessRights::READ_ADD_WRITE),
+            ces_version: None,
+        }
+    }
+
+    fn event_write(metadata: &CesMetadataRef, id: u32, value: u64) -> TransformEntry {
+        event_transform(metadata, id, &test_event(value)).unwrap()
+    }
+
+    fn length_write(metadata: &CesMetadataRef, length: u32) -> TransformEntry {
+        events_length_transform(metadata, length).unwrap()
+    }
+
+    #[test]
+    fn test_events_attributed_to_contract() {
+        let contract = metadata(10);
+        let other_contract = metadata(20);
+        let transforms = vec![
+            event_write(&contract, 5, 50),
+            length_write(&contract, 6),
+            event_write(&other_contract, 0, 1000),
+            length_write(&other_contract, 1),
+            event_write(&contract, 6, 60),
+            length_write(&contract, 7),
+        ];
+
+        let events = parse_events_from_transforms(&transforms, &contract, &test_schemas()).unwrap();
+
+        let events: Vec<(u32, Option<u32>, u64)> = events
+            .into_iter()
+            .map(|ContextualEvent { context, event }| {
+                assert_eq!(context.contract_hash, contract.contract_hash);
+                let value = event.fields[0].1.clone().into_t().unwrap();
+                (context.event_id, context.transform_index, value)
+            })
+            .collect();
+        assert_eq!(events, vec![(5, Some(0), 50), (6, Some(4), 60)]);
+    }
+
+    #[test]
+    fn test_no_events_without_length_write() {
+        let contract = metadata(10);
+        let transforms = vec![event_write(&contract, 0, 1)];
+
+        let events = parse_events_from_transforms(&transforms, &contract, &test_schemas()).unwrap();
+
+        assert!(events.is_empty());
+    }
+
+    #[test]
+    fn test_ces_version_gating() {
+        let mut contract = metadata(10);
+        let transforms = vec![event_write(&contract, 0, 1), length_write(&contract, 1)];
+
+        contract.ces_version = Some("1.1".to_string());
+        let events = parse_events_from_transforms(&transforms, &contract, &test_schemas()).unwrap();
+        assert_eq!(events.len(), 1);
+
+        contract.ces_version = Some("2.0".to_string());
+        let result = parse_events_from_transforms(&transforms, &contract, &test_schemas());
+        assert!(matches!(
+            result,
+            Err(ToolkitError::UnsupportedCesVersion(version)) if version == "2.0"
+        ));
+    }
+}
@@ -0,0 +1,150 @@
+use casper_event_standard::Schemas;
+use casper_hashing::Digest;
+use futures::future;
+
+use crate::error::ToolkitError;
+use crate::event::ContextualEvent;
+use crate::fetcher::parse_stored_event;
+use crate::metadata::CesMetadataRef;
+use crate::rpc::client::CasperClient;
+use crate::source::GlobalStateSource;
+
+/// Fetches events from multiple nodes, accepting them only if enough nodes agree.
+///
+/// Useful for detecting misbehaving or stale RPC providers. All the nodes are queried at the
+/// same state root hash, so honest nodes always return identical data.
+pub struct QuorumFetcher<S = CasperClient> {
+    pub sources: Vec<S>,
+    pub ces_metadata: CesMetadataRef,
+    /// Minimum number of nodes returning identical event bytes.
+    pub quorum: usize,
+}
+
+/// Event confirmed by the quorum of nodes.
+#[derive(Debug)]
+pub struct VerifiedEvent {
+    pub event: ContextualEvent,
+    /// Number of nodes that returned the event bytes.
+    pub agreeing_nodes: usize,
+    /// Nodes that failed or returned different bytes.
+    pub disagreements: Vec<Disagreement>,
+}
+
+/// Response of the node that does not match the quorum.
+#[derive(Debug)]
+pub struct Disagreement {
+    /// Index of the node in `QuorumFetcher::sources`.
+    pub node_index: usize,
+    /// Raw event bytes returned by the node, or the read error.
+    pub response: Result<Vec<u8>, ToolkitError>,
+}
+
+impl<S: GlobalStateSource> QuorumFetcher<S> {
+    /// Reads the event from all the nodes at the given state root hash.
+    ///
+    /// Event is returned only if at least `quorum` nodes returned the same bytes, and no other
+    /// response got the same support.
+    pub async fn fetch_event_at(
+        &self,
+        state_root_hash: Digest,
+        id: u32,
+        event_schema: &Schemas,
+    ) -> Result<VerifiedEvent, ToolkitError> {
+        let events_data_uref = &self.ces_metadata.events_data;
+        let item_key = id.to_string();
+        let responses = future::join_all(self.sources.iter().map(|source| async {
+            let event_value = source
+                .get_stored_clvalue_from_dict(state_root_hash, events_data_uref, &item_key)
+                .await?;
+            Ok(event_value.inner_bytes().to_vec())
+        }))
+        .await;
+
+        // Count nodes returning every distinct value.
+        let mut votes: Vec<(&Vec<u8>, usize)> = vec![];
+        for event_bytes in responses.iter().flatten() {
+            match votes.iter_mut().find(|(bytes, _)| *bytes == event_bytes) {
+                Some((_, count)) => *count += 1,
+                None => votes.push((event_bytes, 1)),
+            }
+        }
+        votes.sort_by(|(_, a), (_, b)| b.cmp(a));
+
+        let agreeing_nodes = votes.first().map_or(0, |(_, count)| *count);
+        let is_tie = votes
+            .get(1)
+            .is_some_and(|(_, count)| *count == agreeing_nodes);
+        if agreeing_nodes < self.quorum.max(1) || is_tie {
+            return Err(ToolkitError::QuorumNotReached {
+                agreeing_nodes,
+                quorum: self.quorum,
+            });
+        }
+
+        let accepted_bytes = votes[0].0.clone();
+        let event = parse_stored_event(&self.ces_metadata, id, &accepted_bytes, event_schema)?;
+        let disagreements = responses
+            .into_iter()
+            .enumerate()
+            .filter(|(_, response)| !matches!(response, Ok(bytes) if *bytes == accepted_bytes))
+            .map(|(node_index, response)| Disagreement {
+                node_index,
+                response,
+            })
+            .collect();
+
+        Ok(VerifiedEvent {
+            event,
+            agreeing_nodes,
+            disagreements,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::source::MemorySource;
+    use crate::test_support::{test_event, test_schemas};
+
+    #[tokio::test]
+    async fn test_quorum_of_nodes() {
+        let schemas = test_schemas();
+
+        // Third node returns tampered event.
+        let mut sources = vec![];
+        let mut ces_metadata = None;
+        for value in [10, 10, 11] {
+            let mut source = MemorySource::new();
+            let metadata = source.add_ces_contract([1; 32], &schemas).unwrap();
+            source.push_event(&metadata, &test_event(value)).unwrap();
+            sources.push(source);
+            ces_metadata = Some(metadata);
+        }
+        let mut fetcher = QuorumFetcher {
+            sources,
+            ces_metadata: ces_metadata.unwrap(),
+            quorum: 2,
+        };
+
+        let verified = fetcher
+            .fetch_event_at(Digest::default(), 0, &schemas)
+            .await
+            .unwrap();
+        let value: u64 = verified.event.event.fields[0].1.clone().into_t().unwrap();
+        assert_eq!(value, 10);
+        assert_eq!(verified.agreeing_nodes, 2);
+        assert_eq!(verified.disagreements.len(), 1);
+        assert_eq!(verified.disagreements[0].node_index, 2);
+
+        fetcher.quorum = 3;
+        let result = fetcher.fetch_event_at(Digest::default(), 0, &schemas).await;
+        assert!(matches!(
+            result,
+            Err(ToolkitError::QuorumNotReached {
+                agreeing_nodes: 2,
+                quorum: 3
+            })
+        ));
+    }
+}
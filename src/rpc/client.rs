@@ -1,29 +1,198 @@
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use casper_client::rpcs::common::BlockIdentifier;
+use casper_client::rpcs::results::{
+    GetBlockResult, GetDeployResult, GetDictionaryItemResult, GetStateRootHashResult,
+    QueryGlobalStateResult,
+};
 use casper_client::types::StoredValue;
 use casper_event_standard::casper_types;
 use casper_hashing::Digest;
 use casper_types::{CLValue, HashAddr, URef};
+use serde_json::json;
 
 use crate::error::ToolkitError;
+use crate::rpc::endpoints::{EndpointHealth, EndpointPool, EndpointSelection};
 use crate::rpc::id_generator::JsonRpcIdGenerator;
+use crate::rpc::proof;
+use crate::rpc::rate_limit::{RateLimitMetrics, RateLimiter};
+use crate::rpc::retry::{retry, RetryPolicy};
+use crate::rpc::state_root_cache::StateRootCache;
+use crate::rpc::transport::Transport;
+use crate::rpc::types::{BlockInfo, ContractPackageInfo, DeployExecution, NodeStatus};
+use crate::source::GlobalStateSource;
 
 use super::compat::{self, uref_to_client_types};
 
 pub const DEFAULT_MAINNET_RPC_ENDPOINT: &str = "https://mainnet.casper-node.xyz/rpc";
 pub const DEFAULT_TESTNET_RPC_ENDPOINT: &str = "https://testnet.casper-node.xyz/rpc";
 
+// JSON-RPC methods of the node.
+const GET_STATE_ROOT_HASH_METHOD: &str = "chain_get_state_root_hash";
+const GET_BLOCK_METHOD: &str = "chain_get_block";
+const GET_DEPLOY_METHOD: &str = "info_get_deploy";
+const GET_STATUS_METHOD: &str = "info_get_status";
+const QUERY_GLOBAL_STATE_METHOD: &str = "query_global_state";
+const GET_DICTIONARY_ITEM_METHOD: &str = "state_get_dictionary_item";
+
 pub struct CasperClient {
-    rpc_endpoint: String,
+    endpoints: EndpointPool,
+    transport: Transport,
     id_generator: JsonRpcIdGenerator,
+    retry_policy: RetryPolicy,
+    rate_limiter: Option<RateLimiter>,
+    verify_merkle_proofs: bool,
+    trusted_block_hash: Option<Digest>,
+    max_batch_size: usize,
+    // Set once the node rejected a batch request.
+    batches_rejected: AtomicBool,
+    state_root_cache: Option<StateRootCache>,
 }
 
 impl CasperClient {
     pub fn new(rpc_endpoint: &str) -> Self {
         Self {
-            rpc_endpoint: rpc_endpoint.to_string(),
+            endpoints: EndpointPool::new(rpc_endpoint),
+            transport: Transport::new(),
             id_generator: JsonRpcIdGenerator::default(),
+            retry_policy: RetryPolicy::disabled(),
+            rate_limiter: None,
+            verify_merkle_proofs: false,
+            trusted_block_hash: None,
+            max_batch_size: 1,
+            batches_rejected: AtomicBool::new(false),
+            state_root_cache: None,
+        }
+    }
+
+    /// Reuses the latest state root hash for the given time, or until a newer block is
+    /// observed - see `observe_block`. Reads of many events then cost a single state root
+    /// hash request per block.
+    ///
+    /// The latest state root hash is then read from the latest block with `chain_get_block`,
+    /// instead of `chain_get_state_root_hash`, as the block height is needed. Block from a
+    /// lagging endpoint does not replace newer cached state root hash.
+    pub fn with_state_root_cache(mut self, ttl: Duration) -> Self {
+        self.state_root_cache = Some(StateRootCache::new(ttl));
+        self
+    }
+
+    /// Tells the client about a new block, replacing cached state root hash if the block is
+    /// newer. Empty or expired cache is not filled, as the block may be a historical one.
+    /// Blocks fetched through the client are observed automatically.
+    pub fn observe_block(&self, block: &BlockInfo) {
+        if let Some(cache) = &self.state_root_cache {
+            cache.observe_block(block.state_root_hash, block.height);
+        }
+    }
+
+    /// Drops cached state root hash, so the next read asks the node.
+    pub fn invalidate_state_root_cache(&self) {
+        if let Some(cache) = &self.state_root_cache {
+            cache.invalidate();
         }
     }
 
+    /// Verifies Merkle proofs of all the global state values against the state root hash they
+    /// are read at.
+    ///
+    /// Proofs only tie the values to the state root hash, which has to be trusted itself -
+    /// pass it to `Fetcher::at_state_root`, or set trusted block with `with_trusted_block_hash`.
+    /// Otherwise the latest state root hash, and blocks fetched by height, come from the
+    /// block headers validated against their own hashes, which the node can forge. Deploy
+    /// results are not verified.
+    pub fn with_merkle_proof_verification(mut self) -> Self {
+        self.verify_merkle_proofs = true;
+        self
+    }
+
+    /// Reads the latest state from the block with given hash, obtained from a trusted party.
+    /// Block header is validated against the hash, so its state root hash can be trusted too.
+    ///
+    /// State root hash cache is not used then. The latest block is still the one reported by
+    /// the node.
+    pub fn with_trusted_block_hash(mut self, block_hash: Digest) -> Self {
+        self.trusted_block_hash = Some(block_hash);
+        self
+    }
+
+    /// Sets policy of repeating failed calls - by default every call is made once.
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Limits the rate of requests sent through this client, including retries and requests
+    /// made by concurrent tasks. Up to `burst` requests can be sent at once.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `requests_per_second` is not a positive, finite number.
+    pub fn with_rate_limit(mut self, requests_per_second: f64, burst: u32) -> Self {
+        self.rate_limiter = Some(RateLimiter::new(requests_per_second, burst));
+        self
+    }
+
+    /// Returns metrics of the rate limiter, if enabled.
+    pub fn rate_limit_metrics(&self) -> Option<RateLimitMetrics> {
+        self.rate_limiter.as_ref().map(RateLimiter::metrics)
+    }
+
+    /// Adds endpoint used when the previous ones fail, or for spreading the load - see
+    /// `with_endpoint_selection`.
+    pub fn with_endpoint(mut self, rpc_endpoint: &str) -> Self {
+        self.endpoints.add(rpc_endpoint);
+        self
+    }
+
+    pub fn with_endpoint_selection(mut self, endpoint_selection: EndpointSelection) -> Self {
+        self.endpoints.set_selection(endpoint_selection);
+        self
+    }
+
+    /// Sets time for which failing endpoint is used only as a last resort.
+    pub fn with_endpoint_cooldown(mut self, cooldown: Duration) -> Self {
+        self.endpoints.set_cooldown(cooldown);
+        self
+    }
+
+    /// Uses the given HTTP client e.g. with tuned connection pool, or shared with other
+    /// clients. By default every `CasperClient` keeps its own pool of kept alive connections.
+    pub fn with_http_client(mut self, http_client: reqwest::Client) -> Self {
+        self.transport.set_http_client(http_client);
+        self
+    }
+
+    /// Reads up to `max_batch_size` dictionary items with a single JSON-RPC batch request,
+    /// e.g. when fetching events range. Once the node rejects a batch, e.g. not supporting
+    /// batch requests, items are read one by one.
+    pub fn with_batch_requests(mut self, max_batch_size: usize) -> Self {
+        self.max_batch_size = max_batch_size.max(1);
+        self
+    }
+
+    /// Adds HTTP header sent with every request e.g. API key of the RPC provider.
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.transport.add_header(name, value);
+        self
+    }
+
+    /// Sends `Authorization: Bearer` header with every request.
+    pub fn with_bearer_token(mut self, token: &str) -> Self {
+        self.transport.set_bearer_token(token);
+        self
+    }
+
+    /// Sets timeout of every single request, from connecting until the response is read.
+    /// Retry policy deadline still applies to the whole call.
+    pub fn with_request_timeout(mut self, request_timeout: Duration) -> Self {
+        self.transport.set_request_timeout(request_timeout);
+        self
+    }
+
     pub fn default_mainnet() -> Self {
         Self::new(DEFAULT_MAINNET_RPC_ENDPOINT)
     }
@@ -32,24 +201,119 @@ impl CasperClient {
         Self::new(DEFAULT_TESTNET_RPC_ENDPOINT)
     }
 
-    // Fetch latest state root hash.
-    pub(crate) async fn get_state_root_hash(&self) -> Result<Digest, ToolkitError> {
-        // No block given means the latest available.
-        let block_identifier = None;
+    /// Checks every endpoint by requesting the latest state root hash, updating its health.
+    pub async fn check_health(&self) -> Vec<EndpointHealth> {
+        let checks = (0..self.endpoints.len()).map(|index| async move {
+            self.wait_for_rate_limit().await;
+            let rpc_id = self.id_generator.next_id().into();
+            let rpc_endpoint = self.endpoints.url(index);
+            let response = self
+                .transport
+                .send_request::<GetStateRootHashResult>(
+                    rpc_id,
+                    rpc_endpoint,
+                    GET_STATE_ROOT_HASH_METHOD,
+                    None,
+                )
+                .await;
 
-        // Common parameters.
+            let healthy = response.is_ok();
+            match healthy {
+                true => self.endpoints.mark_healthy(index),
+                false => self.endpoints.mark_unhealthy(index),
+            }
+
+            EndpointHealth {
+                rpc_endpoint: rpc_endpoint.to_string(),
+                healthy,
+            }
+        });
+
+        futures::future::join_all(checks).await
+    }
+
+    /// Makes the call according to the retry policy, trying all the endpoints in every attempt.
+    ///
+    /// Calls reading global state at the given state root hash also fall through node errors,
+    /// as lagging node may not have the state yet.
+    async fn call<'a, T, F, Fut>(
+        &'a self,
+        state_root_hash: Option<&Digest>,
+        call: F,
+    ) -> Result<(T, usize), ToolkitError>
+    where
+        F: Fn(&'a str) -> Fut,
+        Fut: Future<Output = Result<T, ToolkitError>>,
+    {
+        retry(&self.retry_policy, || async {
+            let mut first_error = None;
+            for index in self.endpoints.order(state_root_hash) {
+                self.wait_for_rate_limit().await;
+                let error = match call(self.endpoints.url(index)).await {
+                    Ok(value) => {
+                        self.endpoints.mark_healthy(index);
+                        return Ok((value, index));
+                    }
+                    Err(e) if e.is_retryable() => {
+                        self.endpoints.mark_unhealthy(index);
+                        e
+                    }
+                    Err(
+                        e @ (ToolkitError::RpcError { .. }
+                        | ToolkitError::InvalidMerkleProof { .. }),
+                    ) if state_root_hash.is_some() => e,
+                    Err(e) => return Err(e),
+                };
+                first_error.get_or_insert(error);
+            }
+
+            Err(first_error.expect("at least one endpoint"))
+        })
+        .await
+    }
+
+    async fn wait_for_rate_limit(&self) {
+        if let Some(rate_limiter) = &self.rate_limiter {
+            rate_limiter.acquire().await;
+        }
+    }
+
+    /// Requests the block, checking that it is the requested one and matches its hash.
+    async fn request_block(
+        &self,
+        rpc_endpoint: &str,
+        block_identifier: Option<BlockIdentifier>,
+    ) -> Result<GetBlockResult, ToolkitError> {
         let rpc_id = self.id_generator.next_id().into();
-        let verbosity = casper_client::Verbosity::Low;
+        let params = block_identifier
+            .map(|block_identifier| json!({ "block_identifier": block_identifier }));
+        let result = self
+            .transport
+            .send_request(rpc_id, rpc_endpoint, GET_BLOCK_METHOD, params)
+            .await?;
+        crate::rpc::utils::validate_block(block_identifier, &result)?;
 
-        let response = casper_client::get_state_root_hash(
-            rpc_id,
-            &self.rpc_endpoint,
-            verbosity,
-            block_identifier,
-        )
-        .await?;
+        Ok(result)
+    }
 
-        let state_root_hash = match response.result.state_root_hash {
+    async fn get_node_state_root_hash(
+        &self,
+        block_identifier: Option<BlockIdentifier>,
+    ) -> Result<(Digest, usize), ToolkitError> {
+        let (response, endpoint_index) = self
+            .call(None, |rpc_endpoint| async move {
+                let rpc_id = self.id_generator.next_id().into();
+                let params = block_identifier
+                    .map(|block_identifier| json!({ "block_identifier": block_identifier }));
+                let result: GetStateRootHashResult = self
+                    .transport
+                    .send_request(rpc_id, rpc_endpoint, GET_STATE_ROOT_HASH_METHOD, params)
+                    .await?;
+                Ok(result)
+            })
+            .await?;
+
+        let state_root_hash = match response.state_root_hash {
             Some(v) => Ok(v),
             None => Err(ToolkitError::UnexpectedError {
                 context: "empty state root hash".into(),
@@ -57,50 +321,185 @@ impl CasperClient {
         }?;
         let state_root_hash = compat::digest_from_client_types(&state_root_hash)?;
 
-        Ok(state_root_hash)
+        Ok((state_root_hash, endpoint_index))
+    }
+
+    /// Fetches the block, validated against its hash.
+    async fn fetch_block(
+        &self,
+        block_identifier: Option<BlockIdentifier>,
+    ) -> Result<(BlockInfo, usize), ToolkitError> {
+        let (response, endpoint_index) = self
+            .call(None, |rpc_endpoint| {
+                self.request_block(rpc_endpoint, block_identifier)
+            })
+            .await?;
+        let block = response.block.ok_or(ToolkitError::MissingBlock)?;
+
+        let header = block.header();
+        let deploy_hashes: Result<Vec<Digest>, ToolkitError> = block
+            .body()
+            .deploy_hashes()
+            .map(|deploy_hash| compat::digest_from_client_types(&deploy_hash.inner()))
+            .collect();
+        let block_info = BlockInfo {
+            hash: compat::digest_from_client_types(&block.hash().inner())?,
+            height: header.height(),
+            timestamp: compat::timestamp_millis_from_client_types(&header.timestamp())?,
+            state_root_hash: compat::digest_from_client_types(&header.state_root_hash())?,
+            deploy_hashes: deploy_hashes?,
+        };
+
+        Ok((block_info, endpoint_index))
+    }
+
+    /// Verifies the proof if enabled, returning the proven value.
+    fn verify_merkle_proof(
+        &self,
+        merkle_proof: &str,
+        state_root_hash: &Digest,
+        key: &casper_types::Key,
+    ) -> Result<Option<casper_types::StoredValue>, ToolkitError> {
+        if !self.verify_merkle_proofs {
+            return Ok(None);
+        }
+        proof::verify_merkle_proof(merkle_proof, state_root_hash, key).map(Some)
     }
 
+    /// Converts dictionary item returned by the node, using its proven value if proofs are
+    /// verified.
+    fn dictionary_item_value(
+        &self,
+        result: GetDictionaryItemResult,
+        state_root_hash: &Digest,
+        proof_key: &casper_types::Key,
+    ) -> Result<CLValue, ToolkitError> {
+        if let Some(proven_value) =
+            self.verify_merkle_proof(&result.merkle_proof, state_root_hash, proof_key)?
+        {
+            return proof::unwrap_dictionary_value(proven_value);
+        }
+
+        let clvalue = match result.stored_value {
+            casper_client::types::StoredValue::CLValue(v) => Ok(v),
+            _ => Err(ToolkitError::UnexpectedStoredValueType {
+                expected_type: "clvalue",
+            }),
+        }?;
+        let clvalue = compat::clvalue_from_client_types(&clvalue)?;
+
+        Ok(clvalue)
+    }
+
+    /// Queries the global state, returning the value together with the proven one - present
+    /// only if proofs are verified.
     async fn query_global_state(
         &self,
         state_root_hash: Digest,
-        key: casper_client_types::Key,
+        key: casper_types::Key,
         path: Vec<String>,
-    ) -> Result<StoredValue, ToolkitError> {
+    ) -> Result<(StoredValue, Option<casper_types::StoredValue>), ToolkitError> {
         // Wrap state root hash.
+        let pinned_state_root_hash = state_root_hash;
         let state_root_hash = compat::digest_to_client_types(&state_root_hash)?;
         let global_state_identifier =
             casper_client::rpcs::GlobalStateIdentifier::StateRootHash(state_root_hash);
+        let proof_key = key;
+        let key = compat::key_to_client_types(&key)?;
 
-        // Common parameters.
-        let rpc_id = self.id_generator.next_id().into();
-        let verbosity = casper_client::Verbosity::Low;
+        let path = &path;
+        let (response, _) = self
+            .call(Some(&pinned_state_root_hash), |rpc_endpoint| async move {
+                let rpc_id = self.id_generator.next_id().into();
+                let params = json!({
+                    "state_identifier": global_state_identifier,
+                    "key": key.to_formatted_string(),
+                    "path": path,
+                });
+                let result: QueryGlobalStateResult = self
+                    .transport
+                    .send_request(
+                        rpc_id,
+                        rpc_endpoint,
+                        QUERY_GLOBAL_STATE_METHOD,
+                        Some(params),
+                    )
+                    .await?;
+                let proven_value = self.verify_merkle_proof(
+                    &result.merkle_proof,
+                    &pinned_state_root_hash,
+                    &proof_key,
+                )?;
+                Ok((result.stored_value, proven_value))
+            })
+            .await?;
+
+        Ok(response)
+    }
+}
 
-        let response = casper_client::query_global_state(
-            rpc_id,
-            &self.rpc_endpoint,
-            verbosity,
-            global_state_identifier,
-            key,
-            path,
-        )
-        .await?;
-        let stored_value = response.result.stored_value;
+#[async_trait]
+impl GlobalStateSource for CasperClient {
+    async fn get_state_root_hash(
+        &self,
+        block_identifier: Option<BlockIdentifier>,
+    ) -> Result<Digest, ToolkitError> {
+        // Latest state is read from the trusted block, if set.
+        let block_identifier = match (block_identifier, self.trusted_block_hash) {
+            (None, Some(block_hash)) => Some(BlockIdentifier::Hash(
+                casper_client::types::BlockHash::new(compat::digest_to_client_types(&block_hash)?),
+            )),
+            (block_identifier, _) => block_identifier,
+        };
+
+        // Only the latest state root hash is cached.
+        let cache = self
+            .state_root_cache
+            .as_ref()
+            .filter(|_| block_identifier.is_none());
+        if let Some(state_root_hash) = cache.and_then(StateRootCache::get) {
+            return Ok(state_root_hash);
+        }
 
-        Ok(stored_value)
+        // Block header is verified, and its height tells if the cached state root is outdated.
+        let from_block =
+            self.verify_merkle_proofs || self.trusted_block_hash.is_some() || cache.is_some();
+        let (state_root_hash, endpoint_index) = match from_block {
+            true => {
+                let (block, endpoint_index) = self.fetch_block(block_identifier).await?;
+                if let Some(cache) = cache {
+                    cache.put_latest(block.state_root_hash, block.height);
+                }
+                (block.state_root_hash, endpoint_index)
+            }
+            false => self.get_node_state_root_hash(block_identifier).await?,
+        };
+        self.endpoints
+            .record_state_root(state_root_hash, endpoint_index);
+
+        Ok(state_root_hash)
     }
 
-    pub(crate) async fn get_contract_named_keys(
+    async fn get_contract_named_keys(
         &self,
+        state_root_hash: Digest,
         contract_hash: HashAddr,
     ) -> Result<casper_types::contracts::NamedKeys, ToolkitError> {
-        // Fetch latest state root hash.
-        let state_root_hash = self.get_state_root_hash().await?;
-
         // Contract is stored directly at given hash.
-        let key = casper_client_types::Key::Hash(contract_hash);
+        let key = casper_types::Key::Hash(contract_hash);
         let path = vec![];
 
-        let stored_value = self.query_global_state(state_root_hash, key, path).await?;
+        let (stored_value, proven_value) =
+            self.query_global_state(state_root_hash, key, path).await?;
+        if let Some(proven_value) = proven_value {
+            return match proven_value {
+                casper_types::StoredValue::Contract(v) => Ok(v.named_keys().clone()),
+                _ => Err(ToolkitError::UnexpectedStoredValueType {
+                    expected_type: "contract",
+                }),
+            };
+        }
+
         let contract = match stored_value {
             casper_client::types::StoredValue::Contract(v) => Ok(v),
             _ => Err(ToolkitError::UnexpectedStoredValueType {
@@ -114,19 +513,88 @@ impl CasperClient {
         Ok(contract)
     }
 
-    pub(crate) async fn get_stored_clvalue(
+    async fn get_account_named_keys(
+        &self,
+        state_root_hash: Digest,
+        account_hash: casper_types::account::AccountHash,
+    ) -> Result<casper_types::contracts::NamedKeys, ToolkitError> {
+        let key = casper_types::Key::Account(account_hash);
+        let path = vec![];
+
+        let (stored_value, proven_value) =
+            self.query_global_state(state_root_hash, key, path).await?;
+        if let Some(proven_value) = proven_value {
+            return match proven_value {
+                casper_types::StoredValue::Account(v) => Ok(v.named_keys().clone()),
+                _ => Err(ToolkitError::UnexpectedStoredValueType {
+                    expected_type: "account",
+                }),
+            };
+        }
+
+        let account = match stored_value {
+            casper_client::types::StoredValue::Account(v) => Ok(v),
+            _ => Err(ToolkitError::UnexpectedStoredValueType {
+                expected_type: "account",
+            }),
+        }?;
+
+        crate::rpc::utils::extract_account_named_keys(account)
+    }
+
+    async fn get_contract_package(
+        &self,
+        state_root_hash: Digest,
+        contract_package_hash: HashAddr,
+    ) -> Result<ContractPackageInfo, ToolkitError> {
+        let key = casper_types::Key::Hash(contract_package_hash);
+        let path = vec![];
+
+        let (stored_value, proven_value) =
+            self.query_global_state(state_root_hash, key, path).await?;
+        if let Some(proven_value) = proven_value {
+            return match proven_value {
+                casper_types::StoredValue::ContractPackage(v) => {
+                    Ok(crate::rpc::utils::contract_package_info(&v))
+                }
+                _ => Err(ToolkitError::UnexpectedStoredValueType {
+                    expected_type: "contract package",
+                }),
+            };
+        }
+
+        let contract_package = match stored_value {
+            casper_client::types::StoredValue::ContractPackage(v) => Ok(v),
+            _ => Err(ToolkitError::UnexpectedStoredValueType {
+                expected_type: "contract package",
+            }),
+        }?;
+
+        Ok(crate::rpc::utils::extract_contract_package(
+            contract_package,
+        ))
+    }
+
+    async fn get_stored_clvalue(
         &self,
+        state_root_hash: Digest,
         uref: &casper_types::URef,
     ) -> Result<CLValue, ToolkitError> {
-        // Fetch latest state root hash.
-        let state_root_hash = self.get_state_root_hash().await?;
-
         // Build uref key.
-        let uref = uref_to_client_types(uref)?;
-        let key = casper_client_types::Key::URef(uref);
+        let key = casper_types::Key::URef(*uref);
         let path = vec![];
 
-        let stored_value = self.query_global_state(state_root_hash, key, path).await?;
+        let (stored_value, proven_value) =
+            self.query_global_state(state_root_hash, key, path).await?;
+        if let Some(proven_value) = proven_value {
+            return match proven_value {
+                casper_types::StoredValue::CLValue(v) => Ok(v),
+                _ => Err(ToolkitError::UnexpectedStoredValueType {
+                    expected_type: "clvalue",
+                }),
+            };
+        }
+
         let clvalue = match stored_value {
             casper_client::types::StoredValue::CLValue(v) => Ok(v),
             _ => Err(ToolkitError::UnexpectedStoredValueType {
@@ -138,69 +606,132 @@ impl CasperClient {
         Ok(clvalue)
     }
 
-    pub(crate) async fn get_stored_clvalue_from_dict(
+    async fn get_stored_clvalue_from_dict(
         &self,
+        state_root_hash: Digest,
         dictionary_seed_uref: &URef,
         dictionary_item_key: &str,
     ) -> Result<CLValue, ToolkitError> {
-        // Fetch latest state root hash.
-        let state_root_hash = self.get_state_root_hash().await?;
+        let pinned_state_root_hash = state_root_hash;
         let state_root_hash = compat::digest_to_client_types(&state_root_hash)?;
 
-        // Build dictionary item identifier.
+        let proof_key =
+            casper_types::Key::dictionary(*dictionary_seed_uref, dictionary_item_key.as_bytes());
+        let proof_key = &proof_key;
         let dictionary_seed_uref = uref_to_client_types(dictionary_seed_uref)?;
-        let dictionary_item_key = dictionary_item_key.to_string();
-        let dictionary_item_identifier =
-            casper_client::rpcs::DictionaryItemIdentifier::new_from_seed_uref(
-                dictionary_seed_uref,
-                dictionary_item_key,
-            );
-
-        // Common parameters.
-        let rpc_id = self.id_generator.next_id().into();
-        let verbosity = casper_client::Verbosity::Low;
-
-        let response = casper_client::get_dictionary_item(
-            rpc_id,
-            &self.rpc_endpoint,
-            verbosity,
-            state_root_hash,
-            dictionary_item_identifier,
-        )
-        .await?;
-        let stored_value = response.result.stored_value;
 
-        let clvalue = match stored_value {
-            casper_client::types::StoredValue::CLValue(v) => Ok(v),
-            _ => Err(ToolkitError::UnexpectedStoredValueType {
-                expected_type: "clvalue",
-            }),
-        }?;
-        let clvalue = compat::clvalue_from_client_types(&clvalue)?;
+        let (clvalue, _) = self
+            .call(Some(&pinned_state_root_hash), |rpc_endpoint| async move {
+                let rpc_id = self.id_generator.next_id().into();
+                let params = dictionary_item_params(
+                    state_root_hash,
+                    dictionary_seed_uref,
+                    dictionary_item_key,
+                );
+                let result = self
+                    .transport
+                    .send_request(
+                        rpc_id,
+                        rpc_endpoint,
+                        GET_DICTIONARY_ITEM_METHOD,
+                        Some(params),
+                    )
+                    .await?;
+                self.dictionary_item_value(result, &pinned_state_root_hash, proof_key)
+            })
+            .await?;
 
         Ok(clvalue)
     }
 
-    pub(crate) async fn get_deploy_result(
+    fn max_batch_size(&self) -> usize {
+        match self.batches_rejected.load(Ordering::Relaxed) {
+            true => 1,
+            false => self.max_batch_size,
+        }
+    }
+
+    async fn get_stored_clvalues_from_dict(
+        &self,
+        state_root_hash: Digest,
+        dictionary_seed_uref: &URef,
+        dictionary_item_keys: &[String],
+    ) -> Result<Vec<Result<CLValue, ToolkitError>>, ToolkitError> {
+        let pinned_state_root_hash = state_root_hash;
+        let state_root_hash = compat::digest_to_client_types(&state_root_hash)?;
+
+        let proof_seed_uref = *dictionary_seed_uref;
+        let dictionary_seed_uref = uref_to_client_types(dictionary_seed_uref)?;
+
+        let (results, _) = self
+            .call(Some(&pinned_state_root_hash), |rpc_endpoint| async move {
+                let calls = dictionary_item_keys
+                    .iter()
+                    .map(|dictionary_item_key| {
+                        let rpc_id = self.id_generator.next_id().into();
+                        let params = dictionary_item_params(
+                            state_root_hash,
+                            dictionary_seed_uref,
+                            dictionary_item_key,
+                        );
+                        (rpc_id, params)
+                    })
+                    .collect();
+                let results = self
+                    .transport
+                    .send_batch_request::<GetDictionaryItemResult>(
+                        rpc_endpoint,
+                        GET_DICTIONARY_ITEM_METHOD,
+                        calls,
+                    )
+                    .await?;
+                Ok(results)
+            })
+            .await
+            .inspect_err(|e| {
+                if e.is_batch_rejection() {
+                    self.batches_rejected.store(true, Ordering::Relaxed);
+                }
+            })?;
+
+        let clvalues = results
+            .into_iter()
+            .zip(dictionary_item_keys)
+            .map(|(result, dictionary_item_key)| {
+                let proof_key =
+                    casper_types::Key::dictionary(proof_seed_uref, dictionary_item_key.as_bytes());
+                self.dictionary_item_value(result?, &pinned_state_root_hash, &proof_key)
+            })
+            .collect();
+
+        Ok(clvalues)
+    }
+
+    async fn get_deploy_result(
         &self,
-        deploy_hash: casper_client::types::DeployHash,
-    ) -> Result<casper_types::ExecutionResult, ToolkitError> {
+        deploy_hash: Digest,
+    ) -> Result<DeployExecution, ToolkitError> {
+        let deploy_hash =
+            casper_client::types::DeployHash::new(compat::digest_to_client_types(&deploy_hash)?);
+
         // Approvals originally received by the node are okay.
         let finalized_approvals = false;
 
-        // Common parameters.
-        let rpc_id = self.id_generator.next_id().into();
-        let verbosity = casper_client::Verbosity::Low;
-
-        let response = casper_client::get_deploy(
-            rpc_id,
-            &self.rpc_endpoint,
-            verbosity,
-            deploy_hash,
-            finalized_approvals,
-        )
-        .await?;
-        let mut execution_results = response.result.execution_results;
+        let (response, _) = self
+            .call(None, |rpc_endpoint| async move {
+                let rpc_id = self.id_generator.next_id().into();
+                let params = json!({
+                    "deploy_hash": deploy_hash,
+                    "finalized_approvals": finalized_approvals,
+                });
+                let result: GetDeployResult = self
+                    .transport
+                    .send_request(rpc_id, rpc_endpoint, GET_DEPLOY_METHOD, Some(params))
+                    .await?;
+                Ok(result)
+            })
+            .await?;
+        let mut execution_results = response.execution_results;
 
         let execution_result = match execution_results.len() {
             1 => Ok(execution_results.remove(0)),
@@ -208,9 +739,289 @@ impl CasperClient {
                 context: "execution results count different than 1".into(),
             }),
         }?;
+        let block_hash = compat::digest_from_client_types(&execution_result.block_hash.inner())?;
         let execution_result =
             compat::execution_result_from_client_types(&execution_result.result)?;
 
-        Ok(execution_result)
+        Ok(DeployExecution {
+            block_hash,
+            execution_result,
+        })
+    }
+
+    async fn get_block(
+        &self,
+        block_identifier: BlockIdentifier,
+    ) -> Result<BlockInfo, ToolkitError> {
+        let (block, _) = self.fetch_block(Some(block_identifier)).await?;
+        self.observe_block(&block);
+
+        Ok(block)
+    }
+
+    async fn get_latest_block(&self) -> Result<BlockInfo, ToolkitError> {
+        let (block, _) = self.fetch_block(None).await?;
+        if let Some(cache) = &self.state_root_cache {
+            cache.put_latest(block.state_root_hash, block.height);
+        }
+
+        Ok(block)
+    }
+
+    async fn get_lowest_available_block_height(&self) -> Result<u64, ToolkitError> {
+        let (status, _) = self
+            .call(None, |rpc_endpoint| async move {
+                let rpc_id = self.id_generator.next_id().into();
+                let status: NodeStatus = self
+                    .transport
+                    .send_request(rpc_id, rpc_endpoint, GET_STATUS_METHOD, None)
+                    .await?;
+                Ok(status)
+            })
+            .await?;
+
+        Ok(status.available_block_range.low)
+    }
+}
+
+fn dictionary_item_params(
+    state_root_hash: casper_client_hashing::Digest,
+    dictionary_seed_uref: casper_client_types::URef,
+    dictionary_item_key: &str,
+) -> serde_json::Value {
+    let dictionary_item_identifier =
+        casper_client::rpcs::DictionaryItemIdentifier::new_from_seed_uref(
+            dictionary_seed_uref,
+            dictionary_item_key.to_string(),
+        );
+
+    json!({
+        "state_root_hash": state_root_hash,
+        "dictionary_identifier": dictionary_item_identifier,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fetcher::Fetcher;
+    use crate::source::MemorySource;
+    use crate::test_support::{
+        merkle_state_root_hash, mock_block, test_event, test_schemas, MockNode,
+    };
+
+    #[tokio::test]
+    async fn test_failover_to_healthy_endpoint() {
+        let mut source = MemorySource::new();
+        source.set_state_root_hash(Digest::from([1; 32]));
+        let node = MockNode::start(source).await.unwrap();
+
+        // Nothing listens on the first endpoint.
+        let client =
+            CasperClient::new("http://127.0.0.1:1/rpc").with_endpoint(&node.rpc_endpoint());
+
+        let state_root_hash = client.get_state_root_hash(None).await.unwrap();
+        assert_eq!(state_root_hash, Digest::from([1; 32]));
+
+        let health: Vec<bool> = client
+            .check_health()
+            .await
+            .into_iter()
+            .map(|endpoint| endpoint.healthy)
+            .collect();
+        assert_eq!(health, vec![false, true]);
+    }
+
+    #[tokio::test]
+    async fn test_http_options() {
+        let mut source = MemorySource::new();
+        source.set_state_root_hash(Digest::from([1; 32]));
+        let node = MockNode::start(source).await.unwrap();
+
+        let client = CasperClient::new(&node.rpc_endpoint())
+            .with_header("X-Api-Key", "key")
+            .with_bearer_token("token")
+            .with_request_timeout(Duration::from_millis(100));
+        client.get_state_root_hash(None).await.unwrap();
+        assert_eq!(
+            node.last_request_header("x-api-key").as_deref(),
+            Some("key")
+        );
+        assert_eq!(
+            node.last_request_header("authorization").as_deref(),
+            Some("Bearer token")
+        );
+
+        // Timed out request can be retried.
+        node.set_response_delay(Duration::from_secs(1));
+        let error = client.get_state_root_hash(None).await.unwrap_err();
+        assert!(matches!(
+            &error,
+            ToolkitError::RpcError { error } if matches!(
+                error.as_ref(),
+                casper_client::Error::FailedToGetResponse { error, .. } if error.is_timeout()
+            )
+        ));
+        assert!(error.is_retryable());
+    }
+
+    #[tokio::test]
+    async fn test_state_root_cache() {
+        let mut source = MemorySource::new();
+        source.put_block(mock_block(1, 0, Digest::from([1; 32]), vec![]).unwrap());
+        let node = MockNode::start(source).await.unwrap();
+        let client =
+            CasperClient::new(&node.rpc_endpoint()).with_state_root_cache(Duration::from_secs(60));
+        assert_eq!(
+            client.get_state_root_hash(None).await.unwrap(),
+            Digest::from([1; 32])
+        );
+
+        // New block is not visible until observed.
+        let block = mock_block(2, 0, Digest::from([2; 32]), vec![]).unwrap();
+        node.source_mut().await.put_block(block);
+        assert_eq!(
+            client.get_state_root_hash(None).await.unwrap(),
+            Digest::from([1; 32])
+        );
+        client.get_block(BlockIdentifier::Height(2)).await.unwrap();
+        assert_eq!(
+            client.get_state_root_hash(None).await.unwrap(),
+            Digest::from([2; 32])
+        );
+
+        let block = mock_block(3, 0, Digest::from([3; 32]), vec![]).unwrap();
+        node.source_mut().await.put_block(block);
+        client.invalidate_state_root_cache();
+        assert_eq!(
+            client.get_state_root_hash(None).await.unwrap(),
+            Digest::from([3; 32])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_state_root_cache_ignores_historical_blocks() {
+        let mut source = MemorySource::new();
+        source.put_block(mock_block(1, 0, Digest::from([1; 32]), vec![]).unwrap());
+        source.put_block(mock_block(2, 0, Digest::from([2; 32]), vec![]).unwrap());
+        let node = MockNode::start(source).await.unwrap();
+        let client =
+            CasperClient::new(&node.rpc_endpoint()).with_state_root_cache(Duration::from_secs(60));
+
+        client.get_block(BlockIdentifier::Height(1)).await.unwrap();
+        assert_eq!(
+            client.get_state_root_hash(None).await.unwrap(),
+            Digest::from([2; 32])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_trusted_block_hash() {
+        let trusted_block = mock_block(1, 0, Digest::from([1; 32]), vec![]).unwrap();
+        let mut source = MemorySource::new();
+        source.put_block(trusted_block.clone());
+        source.put_block(mock_block(2, 0, Digest::from([2; 32]), vec![]).unwrap());
+        let node = MockNode::start(source).await.unwrap();
+
+        // Latest state root hash is the trusted one, not the node's latest.
+        let client = CasperClient::new(&node.rpc_endpoint())
+            .with_merkle_proof_verification()
+            .with_trusted_block_hash(trusted_block.hash);
+        assert_eq!(
+            client.get_state_root_hash(None).await.unwrap(),
+            Digest::from([1; 32])
+        );
+        assert_eq!(
+            client
+                .get_state_root_hash(Some(BlockIdentifier::Height(2)))
+                .await
+                .unwrap(),
+            Digest::from([2; 32])
+        );
+
+        let client =
+            CasperClient::new(&node.rpc_endpoint()).with_trusted_block_hash(Digest::from([9; 32]));
+        assert!(client.get_state_root_hash(None).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_batch_dictionary_reads() {
+        let schemas = test_schemas();
+        let mut source = MemorySource::new();
+        let ces_metadata = source.add_ces_contract([1; 32], &schemas).unwrap();
+        for value in 0..5u64 {
+            source
+                .push_event(&ces_metadata, &test_event(value))
+                .unwrap();
+        }
+        let node = MockNode::start(source).await.unwrap();
+
+        let client = CasperClient::new(&node.rpc_endpoint()).with_batch_requests(2);
+        let fetcher = Fetcher::new(client, ces_metadata);
+        let events = fetcher
+            .at_state_root(Digest::default())
+            .fetch_events(0..6, &schemas)
+            .await;
+
+        // Missing event fails on its own.
+        assert_eq!(events.len(), 6);
+        for (id, event) in &events[..5] {
+            let value: u64 = event.as_ref().unwrap().event.fields[0]
+                .1
+                .clone()
+                .into_t()
+                .unwrap();
+            assert_eq!(value, u64::from(*id));
+        }
+        assert!(events[5].1.is_err());
+        assert_eq!(node.request_count(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_merkle_proof_verification() {
+        let schemas = test_schemas();
+        let mut source = MemorySource::new();
+        let ces_metadata = source.add_ces_contract([1; 32], &schemas).unwrap();
+        for value in 0..3u64 {
+            source
+                .push_event(&ces_metadata, &test_event(value))
+                .unwrap();
+        }
+        let state_root_hash = merkle_state_root_hash(&source).unwrap();
+        source.put_block(mock_block(1, 0, state_root_hash, vec![]).unwrap());
+        let node = MockNode::start(source).await.unwrap();
+
+        // Schema and count are read with `query_global_state`, events with single and batch
+        // `state_get_dictionary_item` requests.
+        for batch_size in [1, 2] {
+            let client = CasperClient::new(&node.rpc_endpoint())
+                .with_merkle_proof_verification()
+                .with_batch_requests(batch_size);
+            let fetcher = Fetcher::new(client, ces_metadata.clone());
+            assert_eq!(fetcher.fetch_schema().await.unwrap(), schemas);
+            assert_eq!(fetcher.fetch_events_count().await.unwrap(), 3);
+            let events = fetcher.fetch_events(0..3, &schemas).await.unwrap();
+            assert!(events.iter().all(|(_, event)| event.is_ok()));
+        }
+
+        // Values changed after the block are served with proofs of another state root hash.
+        {
+            let mut source = node.source_mut().await;
+            source.set_events_count(&ces_metadata, 5).unwrap();
+            let event_bytes = test_event(9).to_ces_bytes().unwrap();
+            source
+                .put_event_bytes(&ces_metadata, 0, event_bytes)
+                .unwrap();
+        }
+        let client = CasperClient::new(&node.rpc_endpoint()).with_merkle_proof_verification();
+        let fetcher = Fetcher::new(client, ces_metadata);
+        assert!(matches!(
+            fetcher.fetch_events_count().await,
+            Err(ToolkitError::InvalidMerkleProof { .. })
+        ));
+        assert!(matches!(
+            fetcher.fetch_event(0, &schemas).await,
+            Err(ToolkitError::InvalidMerkleProof { .. })
+        ));
     }
 }
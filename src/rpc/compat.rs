@@ -48,6 +48,12 @@ pub fn execution_result_from_client_types(
     convert_types(input, "client ExecutionResult")
 }
 
+pub fn timestamp_millis_from_client_types(
+    input: &casper_client::types::Timestamp,
+) -> CompatResult<u64> {
+    convert_types(input, "client Timestamp")
+}
+
 pub fn key_from_client_types(input: &casper_client_types::Key) -> CompatResult<casper_types::Key> {
     convert_types(input, "client Key")
 }
@@ -61,3 +67,9 @@ pub fn key_to_client_types(input: &casper_types::Key) -> CompatResult<casper_cli
 pub fn uref_to_client_types(input: &casper_types::URef) -> CompatResult<casper_client_types::URef> {
     convert_types(input, "URef")
 }
+
+pub fn timestamp_millis_to_client_types(
+    input: u64,
+) -> CompatResult<casper_client::types::Timestamp> {
+    convert_types(&input, "Timestamp")
+}
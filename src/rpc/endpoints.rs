@@ -0,0 +1,170 @@
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use casper_hashing::Digest;
+
+/// Time for which failing endpoint is moved to the end of the queue.
+pub const DEFAULT_ENDPOINT_COOLDOWN: Duration = Duration::from_secs(30);
+
+// Number of remembered state root hashes, with the endpoints that returned them.
+const STATE_ROOT_ORIGINS_LIMIT: usize = 256;
+
+/// Order in which endpoints are used.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum EndpointSelection {
+    /// Always start with the first healthy endpoint, using others only when it fails.
+    #[default]
+    Failover,
+    /// Start with the next endpoint on every call, spreading the load.
+    RoundRobin,
+}
+
+/// Health of the single endpoint.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EndpointHealth {
+    pub rpc_endpoint: String,
+    pub healthy: bool,
+}
+
+struct Endpoint {
+    url: String,
+    // Failing endpoint is not preferred until the given time.
+    unhealthy_until: Mutex<Option<Instant>>,
+}
+
+/// Endpoints of `CasperClient`, together with their health.
+pub(crate) struct EndpointPool {
+    endpoints: Vec<Endpoint>,
+    selection: EndpointSelection,
+    cooldown: Duration,
+    next_index: AtomicUsize,
+    state_root_origins: Mutex<VecDeque<(Digest, usize)>>,
+}
+
+impl EndpointPool {
+    pub fn new(rpc_endpoint: &str) -> Self {
+        Self {
+            endpoints: vec![Endpoint::new(rpc_endpoint)],
+            selection: EndpointSelection::default(),
+            cooldown: DEFAULT_ENDPOINT_COOLDOWN,
+            next_index: AtomicUsize::new(0),
+            state_root_origins: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    pub fn add(&mut self, rpc_endpoint: &str) {
+        self.endpoints.push(Endpoint::new(rpc_endpoint));
+    }
+
+    pub fn set_selection(&mut self, selection: EndpointSelection) {
+        self.selection = selection;
+    }
+
+    pub fn set_cooldown(&mut self, cooldown: Duration) {
+        self.cooldown = cooldown;
+    }
+
+    pub fn len(&self) -> usize {
+        self.endpoints.len()
+    }
+
+    pub fn url(&self, index: usize) -> &str {
+        &self.endpoints[index].url
+    }
+
+    /// Indexes of endpoints in order of preference - healthy ones first.
+    ///
+    /// Endpoint that returned the given state root hash is preferred, as it is known to have
+    /// the corresponding global state.
+    pub fn order(&self, state_root_hash: Option<&Digest>) -> Vec<usize> {
+        let count = self.endpoints.len();
+        let origin = state_root_hash.and_then(|state_root_hash| self.origin(state_root_hash));
+        let start = match (origin, self.selection) {
+            (Some(origin), _) => origin,
+            (None, EndpointSelection::Failover) => 0,
+            (None, EndpointSelection::RoundRobin) => {
+                self.next_index.fetch_add(1, Ordering::Relaxed) % count
+            }
+        };
+
+        let (mut healthy, unhealthy): (Vec<usize>, Vec<usize>) = (0..count)
+            .map(|offset| (start + offset) % count)
+            .partition(|index| self.is_healthy(*index));
+        healthy.extend(unhealthy);
+        healthy
+    }
+
+    pub fn is_healthy(&self, index: usize) -> bool {
+        let unhealthy_until = self.endpoints[index].unhealthy_until.lock().unwrap();
+        unhealthy_until.is_none_or(|until| Instant::now() >= until)
+    }
+
+    pub fn mark_healthy(&self, index: usize) {
+        *self.endpoints[index].unhealthy_until.lock().unwrap() = None;
+    }
+
+    pub fn mark_unhealthy(&self, index: usize) {
+        *self.endpoints[index].unhealthy_until.lock().unwrap() =
+            Some(Instant::now() + self.cooldown);
+    }
+
+    pub fn record_state_root(&self, state_root_hash: Digest, index: usize) {
+        // Single endpoint is always used anyway.
+        if self.endpoints.len() == 1 {
+            return;
+        }
+
+        let mut origins = self.state_root_origins.lock().unwrap();
+        if origins.len() >= STATE_ROOT_ORIGINS_LIMIT {
+            origins.pop_front();
+        }
+        origins.push_back((state_root_hash, index));
+    }
+
+    fn origin(&self, state_root_hash: &Digest) -> Option<usize> {
+        let origins = self.state_root_origins.lock().unwrap();
+        origins
+            .iter()
+            .rev()
+            .find(|(origin_state_root_hash, _)| origin_state_root_hash == state_root_hash)
+            .map(|(_, index)| *index)
+    }
+}
+
+impl Endpoint {
+    fn new(url: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            unhealthy_until: Mutex::new(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_endpoints_order() {
+        let mut pool = EndpointPool::new("a");
+        pool.add("b");
+        pool.add("c");
+        assert_eq!(pool.order(None), vec![0, 1, 2]);
+
+        // Failing endpoint goes last.
+        pool.mark_unhealthy(0);
+        assert_eq!(pool.order(None), vec![1, 2, 0]);
+        pool.mark_healthy(0);
+
+        // Pinned reads start with the node that returned the state root hash.
+        let state_root_hash = Digest::from([1; 32]);
+        pool.record_state_root(state_root_hash, 2);
+        assert_eq!(pool.order(Some(&state_root_hash)), vec![2, 0, 1]);
+
+        pool.set_selection(EndpointSelection::RoundRobin);
+        assert_eq!(pool.order(None), vec![0, 1, 2]);
+        assert_eq!(pool.order(None), vec![1, 2, 0]);
+    }
+}
@@ -1,7 +1,14 @@
 pub mod client;
+pub mod endpoints;
+pub mod rate_limit;
+pub mod retry;
+pub mod types;
 
 // NOTE: Temporarily exposing it for usage in Kairos.
 pub mod compat;
 
 pub(crate) mod id_generator;
+pub(crate) mod proof;
+pub(crate) mod state_root_cache;
+pub(crate) mod transport;
 pub(crate) mod utils;
@@ -0,0 +1,330 @@
+//! Verification of global state Merkle proofs returned by the node.
+//!
+//! Proof is a list of `TrieMerkleProof`s, one for every queried key. Each of them consists of
+//! the key, stored value and the steps from the trie leaf up to the root. Hashing the leaf and
+//! every step in turn reconstructs the state root hash.
+
+use casper_event_standard::casper_types;
+use casper_hashing::Digest;
+use casper_types::bytesrepr::{self, Bytes, FromBytes, ToBytes};
+use casper_types::{CLValue, Key, StoredValue};
+
+use crate::error::ToolkitError;
+
+// Number of children of the trie node.
+const RADIX: usize = 256;
+
+// Trie node tags.
+pub(crate) const TRIE_LEAF_TAG: u8 = 0;
+const TRIE_NODE_TAG: u8 = 1;
+pub(crate) const TRIE_EXTENSION_TAG: u8 = 2;
+
+// Pointer tags.
+const LEAF_POINTER_TAG: u8 = 0;
+const NODE_POINTER_TAG: u8 = 1;
+
+// Proof step tags.
+pub(crate) const PROOF_STEP_NODE_TAG: u8 = 0;
+pub(crate) const PROOF_STEP_EXTENSION_TAG: u8 = 1;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Pointer {
+    Leaf(Digest),
+    Node(Digest),
+}
+
+#[derive(Debug)]
+enum ProofStep {
+    Node {
+        hole_index: u8,
+        indexed_pointers_with_hole: Vec<(u8, Pointer)>,
+    },
+    Extension {
+        affix: Bytes,
+    },
+}
+
+/// Verifies the proof against the state root hash, returning the proven value.
+///
+/// Query with a path would have additional proof for every path segment, which are not
+/// checked against the key - so only a single proof of the queried key is accepted.
+pub(crate) fn verify_merkle_proof(
+    merkle_proof: &str,
+    state_root_hash: &Digest,
+    key: &Key,
+) -> Result<StoredValue, ToolkitError> {
+    let proof_bytes = hex::decode(merkle_proof).map_err(|_e| invalid_proof("hex"))?;
+    let (proofs_count, remainder) =
+        u32::from_bytes(&proof_bytes).map_err(|_e| invalid_proof("proofs count"))?;
+    if proofs_count != 1 {
+        return Err(invalid_proof("proofs count"));
+    }
+
+    let (proven_key, proven_value, root, remainder) = parse_and_hash_proof(remainder)?;
+    if root != *state_root_hash {
+        return Err(invalid_proof("state root hash mismatch"));
+    }
+    if proven_key != key.normalize() {
+        return Err(invalid_proof("key mismatch"));
+    }
+    if !remainder.is_empty() {
+        return Err(invalid_proof("trailing bytes"));
+    }
+
+    Ok(proven_value)
+}
+
+/// Extracts the value from the dictionary item stored in the global state.
+///
+/// Node keeps the seed URef and item key next to the value, while RPC returns only the value.
+pub(crate) fn unwrap_dictionary_value(stored_value: StoredValue) -> Result<CLValue, ToolkitError> {
+    let wrapper = match stored_value {
+        StoredValue::CLValue(v) => Ok(v),
+        _ => Err(ToolkitError::UnexpectedStoredValueType {
+            expected_type: "clvalue",
+        }),
+    }?;
+    let (clvalue, _) = CLValue::from_bytes(wrapper.inner_bytes()).map_err(|_e| {
+        ToolkitError::DeserializationError {
+            context: "dictionary value",
+        }
+    })?;
+
+    Ok(clvalue)
+}
+
+// Parses single trie proof, computing state root hash from it.
+fn parse_and_hash_proof(bytes: &[u8]) -> Result<(Key, StoredValue, Digest, &[u8]), ToolkitError> {
+    let (key, remainder) = Key::from_bytes(bytes).map_err(|_e| invalid_proof("key"))?;
+    let (value, remainder) =
+        StoredValue::from_bytes(remainder).map_err(|_e| invalid_proof("stored value"))?;
+
+    // Leaf is serialized exactly like the key and value in the proof.
+    let leaf_length = bytes.len() - remainder.len();
+    let mut leaf_bytes = vec![TRIE_LEAF_TAG];
+    leaf_bytes.extend_from_slice(&bytes[..leaf_length]);
+    let mut hash = Digest::hash_into_chunks_if_necessary(&leaf_bytes);
+
+    let (steps_count, mut remainder) =
+        u32::from_bytes(remainder).map_err(|_e| invalid_proof("steps count"))?;
+    for step_index in 0..steps_count {
+        let (step, rest) = parse_proof_step(remainder)?;
+        remainder = rest;
+
+        let pointer = match step_index {
+            0 => Pointer::Leaf(hash),
+            _ => Pointer::Node(hash),
+        };
+        let step_bytes = match step {
+            ProofStep::Node {
+                hole_index,
+                mut indexed_pointers_with_hole,
+            } => {
+                indexed_pointers_with_hole.push((hole_index, pointer));
+                node_bytes(&indexed_pointers_with_hole)
+            }
+            ProofStep::Extension { affix } => {
+                let mut step_bytes = vec![TRIE_EXTENSION_TAG];
+                affix
+                    .write_bytes(&mut step_bytes)
+                    .map_err(|_e| invalid_proof("affix"))?;
+                write_pointer(&mut step_bytes, &pointer);
+                step_bytes
+            }
+        };
+        hash = Digest::hash_into_chunks_if_necessary(&step_bytes);
+    }
+
+    Ok((key, value, hash, remainder))
+}
+
+fn parse_proof_step(bytes: &[u8]) -> Result<(ProofStep, &[u8]), ToolkitError> {
+    let (tag, remainder) = u8::from_bytes(bytes).map_err(|_e| invalid_proof("step tag"))?;
+    match tag {
+        PROOF_STEP_NODE_TAG => {
+            let (hole_index, remainder) =
+                u8::from_bytes(remainder).map_err(|_e| invalid_proof("hole index"))?;
+            let (pointers_count, mut remainder) =
+                u32::from_bytes(remainder).map_err(|_e| invalid_proof("pointers count"))?;
+            let mut indexed_pointers_with_hole = vec![];
+            for _ in 0..pointers_count {
+                let (index, rest) =
+                    u8::from_bytes(remainder).map_err(|_e| invalid_proof("pointer index"))?;
+                let (pointer, rest) = parse_pointer(rest)?;
+                indexed_pointers_with_hole.push((index, pointer));
+                remainder = rest;
+            }
+            let step = ProofStep::Node {
+                hole_index,
+                indexed_pointers_with_hole,
+            };
+            Ok((step, remainder))
+        }
+        PROOF_STEP_EXTENSION_TAG => {
+            let (affix, remainder) =
+                Bytes::from_bytes(remainder).map_err(|_e| invalid_proof("affix"))?;
+            Ok((ProofStep::Extension { affix }, remainder))
+        }
+        _ => Err(invalid_proof("step tag")),
+    }
+}
+
+fn parse_pointer(bytes: &[u8]) -> Result<(Pointer, &[u8]), ToolkitError> {
+    let (tag, remainder) = u8::from_bytes(bytes).map_err(|_e| invalid_proof("pointer tag"))?;
+    let (hash, remainder) =
+        <[u8; Digest::LENGTH]>::from_bytes(remainder).map_err(|_e| invalid_proof("pointer"))?;
+    let pointer = match tag {
+        LEAF_POINTER_TAG => Pointer::Leaf(Digest::from(hash)),
+        NODE_POINTER_TAG => Pointer::Node(Digest::from(hash)),
+        _ => return Err(invalid_proof("pointer tag")),
+    };
+
+    Ok((pointer, remainder))
+}
+
+pub(crate) fn write_pointer(bytes: &mut Vec<u8>, pointer: &Pointer) {
+    let (tag, hash) = match pointer {
+        Pointer::Leaf(hash) => (LEAF_POINTER_TAG, hash),
+        Pointer::Node(hash) => (NODE_POINTER_TAG, hash),
+    };
+    bytes.push(tag);
+    bytes.extend_from_slice(hash.as_ref());
+}
+
+// Serializes trie node - every child slot is an optional pointer.
+pub(crate) fn node_bytes(indexed_pointers: &[(u8, Pointer)]) -> Vec<u8> {
+    let mut pointer_block: [Option<Pointer>; RADIX] = [None; RADIX];
+    for (index, pointer) in indexed_pointers {
+        pointer_block[*index as usize] = Some(*pointer);
+    }
+
+    let mut bytes = vec![TRIE_NODE_TAG];
+    for maybe_pointer in pointer_block.iter() {
+        match maybe_pointer {
+            None => bytes.push(bytesrepr::OPTION_NONE_TAG),
+            Some(pointer) => {
+                bytes.push(bytesrepr::OPTION_SOME_TAG);
+                write_pointer(&mut bytes, pointer);
+            }
+        }
+    }
+
+    bytes
+}
+
+fn invalid_proof(context: &'static str) -> ToolkitError {
+    ToolkitError::InvalidMerkleProof { context }
+}
+
+#[cfg(test)]
+mod tests {
+    use casper_hashing::ChunkWithProof;
+    use casper_types::{AccessRights, URef};
+
+    use super::*;
+    use crate::test_support::trie::StateTrie;
+
+    // Hashes trie element as the node does - element larger than a chunk is hashed as the Merkle
+    // tree of its chunks.
+    fn trie_hash(bytes: &[u8]) -> Digest {
+        if bytes.len() <= ChunkWithProof::CHUNK_SIZE_BYTES {
+            return Digest::hash(bytes);
+        }
+        Digest::hash_merkle_tree(
+            bytes
+                .chunks(ChunkWithProof::CHUNK_SIZE_BYTES)
+                .map(Digest::hash),
+        )
+    }
+
+    // Builds proof of a value placed in a trie node next to another leaf, under an extension.
+    fn build_proof(key: &Key, value: &StoredValue) -> (String, Digest) {
+        let mut leaf_bytes = vec![TRIE_LEAF_TAG];
+        leaf_bytes.extend(key.to_bytes().unwrap());
+        leaf_bytes.extend(value.to_bytes().unwrap());
+        let leaf_pointer = Pointer::Leaf(trie_hash(&leaf_bytes));
+
+        let sibling_pointer = Pointer::Leaf(Digest::from([7; 32]));
+        let node = node_bytes(&[(3, sibling_pointer), (200, leaf_pointer)]);
+        let node_pointer = Pointer::Node(trie_hash(&node));
+
+        let affix = Bytes::from(vec![1, 2]);
+        let mut extension = vec![TRIE_EXTENSION_TAG];
+        affix.write_bytes(&mut extension).unwrap();
+        write_pointer(&mut extension, &node_pointer);
+        let state_root_hash = trie_hash(&extension);
+
+        let mut proof = 1u32.to_bytes().unwrap();
+        proof.extend(key.to_bytes().unwrap());
+        proof.extend(value.to_bytes().unwrap());
+        proof.extend(2u32.to_bytes().unwrap());
+        proof.extend([PROOF_STEP_NODE_TAG, 200]);
+        proof.extend(1u32.to_bytes().unwrap());
+        proof.push(3);
+        write_pointer(&mut proof, &sibling_pointer);
+        proof.push(PROOF_STEP_EXTENSION_TAG);
+        affix.write_bytes(&mut proof).unwrap();
+
+        (hex::encode(proof), state_root_hash)
+    }
+
+    #[test]
+    fn test_verify_merkle_proof() {
+        let uref = URef::new([1; 32], AccessRights::READ_ADD_WRITE);
+        let key = Key::URef(uref);
+        let value = StoredValue::CLValue(CLValue::from_t(5u32).unwrap());
+        let (proof, state_root_hash) = build_proof(&key.normalize(), &value);
+
+        let proven_value = verify_merkle_proof(&proof, &state_root_hash, &key).unwrap();
+        assert_eq!(proven_value, value);
+
+        let other_state_root_hash = Digest::from([0; 32]);
+        assert!(verify_merkle_proof(&proof, &other_state_root_hash, &key).is_err());
+
+        let other_key = Key::URef(URef::new([2; 32], AccessRights::READ_ADD_WRITE));
+        assert!(verify_merkle_proof(&proof, &state_root_hash, &other_key).is_err());
+
+        // Proofs for a query with a path are not accepted, even if all of them are valid.
+        let single_proof = &hex::decode(&proof).unwrap()[4..];
+        let mut proofs = 2u32.to_bytes().unwrap();
+        proofs.extend(single_proof);
+        proofs.extend(single_proof);
+        let proofs = hex::encode(proofs);
+        assert!(verify_merkle_proof(&proofs, &state_root_hash, &key).is_err());
+    }
+
+    #[test]
+    fn test_verify_merkle_proof_of_chunked_leaf() {
+        let key = Key::URef(URef::new([1; 32], AccessRights::NONE));
+        let large_bytes = Bytes::from(vec![7; ChunkWithProof::CHUNK_SIZE_BYTES + 1]);
+        let value = StoredValue::CLValue(CLValue::from_t(large_bytes).unwrap());
+        let (proof, state_root_hash) = build_proof(&key, &value);
+
+        let proven_value = verify_merkle_proof(&proof, &state_root_hash, &key).unwrap();
+        assert_eq!(proven_value, value);
+    }
+
+    #[test]
+    fn test_verify_state_trie_proofs() {
+        let leaves: Vec<(Key, StoredValue)> = (0..300u32)
+            .map(|id| {
+                let mut addr = [0; 32];
+                addr[..4].copy_from_slice(&id.to_be_bytes());
+                let key = Key::URef(URef::new(addr, AccessRights::NONE));
+                (key, StoredValue::CLValue(CLValue::from_t(id).unwrap()))
+            })
+            .collect();
+        let state_trie = StateTrie::new(leaves.clone()).unwrap();
+        let state_root_hash = state_trie.root_hash();
+
+        // Leaves of both nodes under the root.
+        for (key, value) in [&leaves[0], &leaves[255], &leaves[256], &leaves[299]] {
+            let proof = state_trie.merkle_proof(key).unwrap().unwrap();
+            // Both nodes on the path have all the slots taken, as near the root of the real
+            // trie - serialized node is larger than 8 KiB.
+            assert!(hex::decode(&proof).unwrap().len() > 2 * 255 * (1 + 1 + Digest::LENGTH));
+            let proven_value = verify_merkle_proof(&proof, &state_root_hash, key).unwrap();
+            assert_eq!(proven_value, *value);
+        }
+    }
+}
@@ -0,0 +1,146 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+
+/// Time spent by `CasperClient` waiting for the rate limiter.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RateLimitMetrics {
+    /// Number of requests that passed through the limiter.
+    pub requests: u64,
+    /// Number of requests that had to wait, for a token or for the requests queued before them.
+    pub throttled_requests: u64,
+    /// Waiting time of all the throttled requests, including time spent in the queue.
+    pub total_wait: Duration,
+}
+
+/// Token bucket limiting the rate of requests.
+///
+/// Bucket holds up to `burst` tokens, refilled at `requests_per_second` rate, and every request
+/// takes a single token. Waiting requests are served in FIFO order.
+pub(crate) struct RateLimiter {
+    requests_per_second: f64,
+    burst: f64,
+    bucket: Mutex<Bucket>,
+    requests: AtomicU64,
+    throttled_requests: AtomicU64,
+    total_wait_nanos: AtomicU64,
+}
+
+struct Bucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl RateLimiter {
+    pub fn new(requests_per_second: f64, burst: u32) -> Self {
+        assert!(
+            requests_per_second.is_finite() && requests_per_second > 0.0,
+            "requests per second must be positive and finite, got {}",
+            requests_per_second
+        );
+        let burst = f64::from(burst.max(1));
+
+        Self {
+            requests_per_second,
+            burst,
+            bucket: Mutex::new(Bucket {
+                tokens: burst,
+                last_refill: Instant::now(),
+            }),
+            requests: AtomicU64::new(0),
+            throttled_requests: AtomicU64::new(0),
+            total_wait_nanos: AtomicU64::new(0),
+        }
+    }
+
+    /// Waits until the request is allowed.
+    pub async fn acquire(&self) {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+        let wait_start = Instant::now();
+
+        // Lock is held while sleeping, so the next request waits for its turn.
+        let (mut bucket, queued) = match self.bucket.try_lock() {
+            Ok(bucket) => (bucket, false),
+            Err(_) => (self.bucket.lock().await, true),
+        };
+        self.refill(&mut bucket);
+
+        let throttled = queued || bucket.tokens < 1.0;
+        if bucket.tokens < 1.0 {
+            let wait = Duration::from_secs_f64((1.0 - bucket.tokens) / self.requests_per_second);
+            tokio::time::sleep(wait).await;
+            self.refill(&mut bucket);
+        }
+        bucket.tokens = (bucket.tokens - 1.0).max(0.0);
+
+        if throttled {
+            self.throttled_requests.fetch_add(1, Ordering::Relaxed);
+            self.total_wait_nanos
+                .fetch_add(wait_start.elapsed().as_nanos() as u64, Ordering::Relaxed);
+        }
+    }
+
+    pub fn metrics(&self) -> RateLimitMetrics {
+        RateLimitMetrics {
+            requests: self.requests.load(Ordering::Relaxed),
+            throttled_requests: self.throttled_requests.load(Ordering::Relaxed),
+            total_wait: Duration::from_nanos(self.total_wait_nanos.load(Ordering::Relaxed)),
+        }
+    }
+
+    fn refill(&self, bucket: &mut Bucket) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(bucket.last_refill).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.requests_per_second).min(self.burst);
+        bucket.last_refill = now;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_rate_limiter_throttles_after_burst() {
+        let rate_limiter = RateLimiter::new(100.0, 2);
+
+        let start = Instant::now();
+        for _ in 0..4 {
+            rate_limiter.acquire().await;
+        }
+
+        // Burst is free, then every request waits for 10 ms.
+        assert!(start.elapsed() >= Duration::from_millis(15));
+        let metrics = rate_limiter.metrics();
+        assert_eq!(metrics.requests, 4);
+        assert_eq!(metrics.throttled_requests, 2);
+        assert!(metrics.total_wait >= Duration::from_millis(15));
+    }
+
+    #[tokio::test]
+    async fn test_rate_limiter_counts_queued_requests() {
+        let rate_limiter = RateLimiter::new(100.0, 1);
+
+        // Third request waits in the queue for the second one, then for its own token.
+        futures::future::join_all((0..3).map(|_| rate_limiter.acquire())).await;
+
+        let metrics = rate_limiter.metrics();
+        assert_eq!(metrics.requests, 3);
+        assert_eq!(metrics.throttled_requests, 2);
+        assert!(metrics.total_wait >= Duration::from_millis(25));
+    }
+
+    #[test]
+    #[should_panic(expected = "requests per second must be positive")]
+    fn test_rate_limiter_rejects_zero_rate() {
+        RateLimiter::new(0.0, 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "requests per second must be positive")]
+    fn test_rate_limiter_rejects_nan_rate() {
+        RateLimiter::new(f64::NAN, 1);
+    }
+}
@@ -0,0 +1,152 @@
+use std::future::Future;
+use std::time::Duration;
+
+use rand::Rng;
+use tokio::time::Instant;
+
+use crate::error::ToolkitError;
+
+/// Policy of repeating RPC calls that failed with retryable error, see
+/// `ToolkitError::is_retryable`.
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    /// Maximum number of attempts, including the first one.
+    pub max_attempts: u32,
+    /// Delay before the first retry, doubled with every subsequent one.
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    /// Fraction of the backoff that is randomized, from `0.0` (none) to `1.0` (full jitter).
+    pub jitter: f64,
+    /// Time limit for the whole call, including all the retries.
+    pub deadline: Option<Duration>,
+}
+
+impl RetryPolicy {
+    /// Policy performing every call exactly once.
+    pub fn disabled() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Default::default()
+        }
+    }
+
+    /// Delay before the given retry, counted from 1.
+    fn backoff(&self, retry: u32) -> Duration {
+        let exponent = retry.saturating_sub(1).min(31);
+        let backoff = self
+            .initial_backoff
+            .saturating_mul(1 << exponent)
+            .min(self.max_backoff);
+
+        let jitter = self.jitter.clamp(0.0, 1.0);
+        if jitter == 0.0 {
+            return backoff;
+        }
+        backoff.mul_f64(1.0 - jitter * rand::thread_rng().gen::<f64>())
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            initial_backoff: Duration::from_millis(200),
+            max_backoff: Duration::from_secs(10),
+            jitter: 0.5,
+            deadline: None,
+        }
+    }
+}
+
+/// Runs the call according to the policy, returning the last error once retries are exhausted.
+pub(crate) async fn retry<T, F, Fut>(policy: &RetryPolicy, mut call: F) -> Result<T, ToolkitError>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, ToolkitError>>,
+{
+    let deadline = policy.deadline.map(|deadline| Instant::now() + deadline);
+    let mut attempt = 1;
+
+    loop {
+        let result = match deadline {
+            Some(deadline) => tokio::time::timeout_at(deadline, call())
+                .await
+                .unwrap_or(Err(ToolkitError::Timeout)),
+            None => call().await,
+        };
+
+        let error = match result {
+            Ok(value) => return Ok(value),
+            Err(e) if !e.is_retryable() || attempt >= policy.max_attempts => return Err(e),
+            Err(e) => e,
+        };
+
+        // Give up if there is no time left for another attempt.
+        let backoff = policy.backoff(attempt);
+        if deadline.is_some_and(|deadline| Instant::now() + backoff >= deadline) {
+            return Err(error);
+        }
+
+        tokio::time::sleep(backoff).await;
+        attempt += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_retry_only_retryable_errors() {
+        let policy = RetryPolicy {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(1),
+            ..Default::default()
+        };
+
+        let attempts = AtomicU32::new(0);
+        let result: Result<(), _> = retry(&policy, || async {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            Err(ToolkitError::Timeout)
+        })
+        .await;
+        assert!(matches!(result, Err(ToolkitError::Timeout)));
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+
+        let attempts = AtomicU32::new(0);
+        let result: Result<(), _> = retry(&policy, || async {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            Err(ToolkitError::MissingBlock)
+        })
+        .await;
+        assert!(matches!(result, Err(ToolkitError::MissingBlock)));
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+
+        // Node errors are retried only if caused by its temporary state.
+        for (code, expected_attempts) in [(-32012, 3), (-32004, 3), (-32003, 1)] {
+            let attempts = AtomicU32::new(0);
+            let result: Result<(), _> = retry(&policy, || async {
+                attempts.fetch_add(1, Ordering::SeqCst);
+                Err(rpc_error(code))
+            })
+            .await;
+            assert!(result.is_err());
+            assert_eq!(attempts.load(Ordering::SeqCst), expected_attempts);
+        }
+    }
+
+    fn rpc_error(code: i64) -> ToolkitError {
+        casper_client::Error::ResponseIsRpcError {
+            rpc_id: casper_client::JsonRpcId::from(1),
+            rpc_method: "state_get_dictionary_item",
+            error: jsonrpc_lite::Error {
+                code,
+                message: "node error".to_string(),
+                data: None,
+            },
+        }
+        .into()
+    }
+}
@@ -0,0 +1,116 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use casper_hashing::Digest;
+
+/// Latest state root hash remembered by `CasperClient`, together with its block height.
+///
+/// Cached value is used until the TTL passes, or until a newer block is observed.
+pub(crate) struct StateRootCache {
+    ttl: Duration,
+    entry: Mutex<Option<CachedStateRoot>>,
+}
+
+struct CachedStateRoot {
+    state_root_hash: Digest,
+    block_height: u64,
+    cached_at: Instant,
+}
+
+impl StateRootCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entry: Mutex::new(None),
+        }
+    }
+
+    pub fn get(&self) -> Option<Digest> {
+        let entry = self.entry.lock().unwrap();
+        entry
+            .as_ref()
+            .filter(|entry| entry.cached_at.elapsed() < self.ttl)
+            .map(|entry| entry.state_root_hash)
+    }
+
+    /// Caches state root hash of the latest block, unless the block is older than the cached
+    /// one e.g. read from a lagging endpoint. Block at the same height refreshes the TTL.
+    pub fn put_latest(&self, state_root_hash: Digest, block_height: u64) {
+        let mut entry = self.entry.lock().unwrap();
+        if let Some(cached) = entry.as_ref() {
+            if block_height < cached.block_height {
+                return;
+            }
+        }
+        *entry = Some(CachedStateRoot {
+            state_root_hash,
+            block_height,
+            cached_at: Instant::now(),
+        });
+    }
+
+    /// Replaces cached state root hash, if the block is newer than the cached one. Block may be
+    /// a historical one, so it never fills empty or expired cache.
+    pub fn observe_block(&self, state_root_hash: Digest, block_height: u64) {
+        let mut entry = self.entry.lock().unwrap();
+        let newer = entry.as_ref().is_some_and(|cached| {
+            cached.cached_at.elapsed() < self.ttl && block_height > cached.block_height
+        });
+        if newer {
+            *entry = Some(CachedStateRoot {
+                state_root_hash,
+                block_height,
+                cached_at: Instant::now(),
+            });
+        }
+    }
+
+    pub fn invalidate(&self) {
+        *self.entry.lock().unwrap() = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_state_root_cache() {
+        let cache = StateRootCache::new(Duration::from_secs(60));
+        assert_eq!(cache.get(), None);
+
+        // Observed block does not fill empty cache.
+        cache.observe_block(Digest::from([1; 32]), 10);
+        assert_eq!(cache.get(), None);
+        cache.put_latest(Digest::from([1; 32]), 10);
+        assert_eq!(cache.get(), Some(Digest::from([1; 32])));
+
+        // Older blocks are ignored, newer replace cached state root hash.
+        cache.observe_block(Digest::from([2; 32]), 9);
+        assert_eq!(cache.get(), Some(Digest::from([1; 32])));
+        cache.observe_block(Digest::from([3; 32]), 11);
+        assert_eq!(cache.get(), Some(Digest::from([3; 32])));
+
+        cache.invalidate();
+        assert_eq!(cache.get(), None);
+
+        let expired_cache = StateRootCache::new(Duration::ZERO);
+        expired_cache.put_latest(Digest::from([1; 32]), 10);
+        assert_eq!(expired_cache.get(), None);
+    }
+
+    #[test]
+    fn test_state_root_cache_refresh() {
+        let cache = StateRootCache::new(Duration::from_millis(20));
+        cache.put_latest(Digest::from([1; 32]), 10);
+        std::thread::sleep(Duration::from_millis(30));
+        assert_eq!(cache.get(), None);
+
+        // Newer block does not fill expired cache, while the latest one at the same height
+        // refreshes it.
+        cache.observe_block(Digest::from([2; 32]), 11);
+        assert_eq!(cache.get(), None);
+        cache.put_latest(Digest::from([1; 32]), 10);
+        assert_eq!(cache.get(), Some(Digest::from([1; 32])));
+    }
+}
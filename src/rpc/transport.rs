@@ -0,0 +1,224 @@
+use std::time::Duration;
+
+use casper_client::JsonRpcId;
+use jsonrpc_lite::{JsonRpc, Params};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::error::ToolkitError;
+
+const RPC_API_PATH: &str = "rpc";
+
+/// Sends JSON-RPC requests to the node, with HTTP options of `CasperClient`.
+///
+/// Requests and responses follow `casper_client` format, and failures are reported with
+/// `casper_client::Error` wrapped into `ToolkitError::RpcError`, so both can be used
+/// interchangeably. All the requests go through
+/// the same HTTP client, so connections are kept alive and reused.
+pub(crate) struct Transport {
+    http_client: reqwest::Client,
+    headers: Vec<(String, String)>,
+    bearer_token: Option<String>,
+    request_timeout: Option<Duration>,
+}
+
+impl Transport {
+    pub fn new() -> Self {
+        Self {
+            http_client: reqwest::Client::new(),
+            headers: vec![],
+            bearer_token: None,
+            request_timeout: None,
+        }
+    }
+
+    pub fn set_http_client(&mut self, http_client: reqwest::Client) {
+        self.http_client = http_client;
+    }
+
+    pub fn add_header(&mut self, name: &str, value: &str) {
+        self.headers.push((name.to_string(), value.to_string()));
+    }
+
+    pub fn set_bearer_token(&mut self, token: &str) {
+        self.bearer_token = Some(token.to_string());
+    }
+
+    pub fn set_request_timeout(&mut self, request_timeout: Duration) {
+        self.request_timeout = Some(request_timeout);
+    }
+
+    pub async fn send_request<R: DeserializeOwned>(
+        &self,
+        rpc_id: JsonRpcId,
+        rpc_endpoint: &str,
+        method: &'static str,
+        maybe_params: Option<Value>,
+    ) -> Result<R, ToolkitError> {
+        let rpc_request = match maybe_params {
+            Some(Value::Object(params)) => {
+                JsonRpc::request_with_params(&rpc_id, method, Params::Map(params))
+            }
+            _ => JsonRpc::request(&rpc_id, method),
+        };
+
+        let rpc_response: JsonRpc = self
+            .post(&rpc_id, rpc_endpoint, method, &rpc_request)
+            .await?;
+        parse_response(rpc_id, method, rpc_response)
+    }
+
+    /// Sends multiple calls of the same method in a single JSON-RPC batch request.
+    ///
+    /// Failure of the whole request is returned as an error, while failures of the single
+    /// calls are returned in order of the given params.
+    pub async fn send_batch_request<R: DeserializeOwned>(
+        &self,
+        rpc_endpoint: &str,
+        method: &'static str,
+        calls: Vec<(JsonRpcId, Value)>,
+    ) -> Result<Vec<Result<R, ToolkitError>>, ToolkitError> {
+        let Some((first_rpc_id, _)) = calls.first() else {
+            return Ok(vec![]);
+        };
+        let first_rpc_id = first_rpc_id.clone();
+
+        let rpc_requests: Vec<JsonRpc> = calls
+            .iter()
+            .map(|(rpc_id, params)| match params {
+                Value::Object(params) => {
+                    JsonRpc::request_with_params(rpc_id, method, Params::Map(params.clone()))
+                }
+                _ => JsonRpc::request(rpc_id, method),
+            })
+            .collect();
+        let mut rpc_responses: Vec<JsonRpc> = self
+            .post(&first_rpc_id, rpc_endpoint, method, &rpc_requests)
+            .await?;
+
+        // Responses can come in any order.
+        let results = calls
+            .into_iter()
+            .map(|(rpc_id, _)| {
+                let id = jsonrpc_lite::Id::from(&rpc_id);
+                match rpc_responses
+                    .iter()
+                    .position(|rpc_response| rpc_response.get_id().as_ref() == Some(&id))
+                {
+                    Some(index) => parse_response(rpc_id, method, rpc_responses.swap_remove(index)),
+                    None => Err(casper_client::Error::InvalidRpcResponse {
+                        rpc_id,
+                        rpc_method: method,
+                        response_kind: "Missing",
+                        response: Value::Null,
+                        source: None,
+                    }
+                    .into()),
+                }
+            })
+            .collect();
+
+        Ok(results)
+    }
+
+    /// Posts the request with configured headers, returning JSON response.
+    async fn post<T: DeserializeOwned>(
+        &self,
+        rpc_id: &JsonRpcId,
+        rpc_endpoint: &str,
+        method: &'static str,
+        body: &impl Serialize,
+    ) -> Result<T, ToolkitError> {
+        // Invalid headers are reported when sending the request.
+        let mut request = self.http_client.post(rpc_url(rpc_endpoint));
+        for (name, value) in &self.headers {
+            request = request.header(name, value);
+        }
+        if let Some(token) = &self.bearer_token {
+            request = request.bearer_auth(token);
+        }
+        if let Some(request_timeout) = self.request_timeout {
+            request = request.timeout(request_timeout);
+        }
+
+        let http_response = request.json(body).send().await.map_err(|error| {
+            casper_client::Error::FailedToGetResponse {
+                rpc_id: rpc_id.clone(),
+                rpc_method: method,
+                error,
+            }
+        })?;
+        if let Err(error) = http_response.error_for_status_ref() {
+            return Err(casper_client::Error::ResponseIsHttpError {
+                rpc_id: rpc_id.clone(),
+                rpc_method: method,
+                error,
+            }
+            .into());
+        }
+
+        let response = http_response.json().await.map_err(|error| {
+            casper_client::Error::FailedToParseResponse {
+                rpc_id: rpc_id.clone(),
+                rpc_method: method,
+                error,
+            }
+        })?;
+
+        Ok(response)
+    }
+}
+
+/// Node serves JSON-RPC under `/rpc` path, which can be omitted in the endpoint.
+fn rpc_url(rpc_endpoint: &str) -> String {
+    let rpc_endpoint = rpc_endpoint.trim_end_matches('/');
+    match rpc_endpoint.ends_with(RPC_API_PATH) {
+        true => rpc_endpoint.to_string(),
+        false => format!("{}/{}", rpc_endpoint, RPC_API_PATH),
+    }
+}
+
+fn parse_response<R: DeserializeOwned>(
+    rpc_id: JsonRpcId,
+    method: &'static str,
+    rpc_response: JsonRpc,
+) -> Result<R, ToolkitError> {
+    let response_kind = match &rpc_response {
+        JsonRpc::Request(_) => "Request",
+        JsonRpc::Notification(_) => "Notification",
+        JsonRpc::Success(_) => "Success",
+        JsonRpc::Error(_) => "Error",
+    };
+
+    if let Some(result) = rpc_response.get_result() {
+        let result = serde_json::from_value(result.clone()).map_err(|error| {
+            casper_client::Error::InvalidRpcResponse {
+                rpc_id,
+                rpc_method: method,
+                response_kind,
+                response: json!(rpc_response),
+                source: Some(error),
+            }
+        })?;
+        return Ok(result);
+    }
+
+    if let Some(error) = rpc_response.get_error() {
+        return Err(casper_client::Error::ResponseIsRpcError {
+            rpc_id,
+            rpc_method: method,
+            error: error.clone(),
+        }
+        .into());
+    }
+
+    Err(casper_client::Error::InvalidRpcResponse {
+        rpc_id,
+        rpc_method: method,
+        response_kind,
+        response: json!(rpc_response),
+        source: None,
+    }
+    .into())
+}
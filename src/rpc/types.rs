@@ -0,0 +1,70 @@
+use casper_event_standard::casper_types;
+use casper_hashing::Digest;
+use casper_types::HashAddr;
+use serde::Deserialize;
+
+/// Block data relevant for the events context.
+#[derive(Clone, Debug)]
+pub struct BlockInfo {
+    pub hash: Digest,
+    pub height: u64,
+    /// Block timestamp - milliseconds since UNIX epoch.
+    pub timestamp: u64,
+    pub state_root_hash: Digest,
+    /// Deploys executed in the block, excluding native transfers.
+    pub deploy_hashes: Vec<Digest>,
+}
+
+/// Deploy execution result together with the block it was executed in.
+#[derive(Clone, Debug)]
+pub struct DeployExecution {
+    pub block_hash: Digest,
+    pub execution_result: casper_types::ExecutionResult,
+}
+
+/// Contract version registered in the contract package.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ContractVersionInfo {
+    pub protocol_version_major: u32,
+    pub contract_version: u32,
+    pub contract_hash: HashAddr,
+    /// Disabled version can not be called anymore, but its data stays in the global state.
+    pub enabled: bool,
+}
+
+/// Contract package data relevant for resolving its contracts.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ContractPackageInfo {
+    /// All the versions, ordered from the oldest.
+    pub versions: Vec<ContractVersionInfo>,
+}
+
+impl ContractPackageInfo {
+    /// Returns the newest enabled version - the one called through the package.
+    pub fn latest_enabled_version(&self) -> Option<&ContractVersionInfo> {
+        self.versions.iter().rev().find(|version| version.enabled)
+    }
+
+    pub fn find_version(
+        &self,
+        protocol_version_major: u32,
+        contract_version: u32,
+    ) -> Option<&ContractVersionInfo> {
+        self.versions.iter().find(|version| {
+            version.protocol_version_major == protocol_version_major
+                && version.contract_version == contract_version
+        })
+    }
+}
+
+/// Part of the node status relevant for reading the history.
+#[derive(Clone, Debug, Deserialize)]
+pub(crate) struct NodeStatus {
+    pub available_block_range: AvailableBlockRange,
+}
+
+/// Blocks synced by the node - only the lower bound is needed.
+#[derive(Clone, Debug, Deserialize)]
+pub(crate) struct AvailableBlockRange {
+    pub low: u64,
+}
@@ -1,20 +1,36 @@
 use std::collections::BTreeMap;
 
-use casper_client::types::Contract;
+use casper_client::rpcs::common::BlockIdentifier;
+use casper_client::rpcs::results::GetBlockResult;
+use casper_client::types::{Account, Contract, ContractPackage, NamedKey};
+use casper_client::ValidateResponseError;
 use casper_event_standard::casper_types;
 use casper_types::Key;
 
 use crate::error::ToolkitError;
 
 use super::compat;
+use super::types::{ContractPackageInfo, ContractVersionInfo};
 
 /// Transforms a contract's named keys into a proper `NamedKeys` (from `casper_types`).
 ///
 pub fn extract_named_keys(
     contract: Contract,
 ) -> Result<casper_types::contracts::NamedKeys, ToolkitError> {
-    let named_keys_result: Result<BTreeMap<String, Key>, ToolkitError> = contract
-        .named_keys()
+    convert_named_keys(contract.named_keys())
+}
+
+/// Transforms an account's named keys into a proper `NamedKeys` (from `casper_types`).
+pub fn extract_account_named_keys(
+    account: Account,
+) -> Result<casper_types::contracts::NamedKeys, ToolkitError> {
+    convert_named_keys(account.named_keys())
+}
+
+fn convert_named_keys<'a>(
+    named_keys: impl Iterator<Item = &'a NamedKey>,
+) -> Result<casper_types::contracts::NamedKeys, ToolkitError> {
+    let named_keys_result: Result<BTreeMap<String, Key>, ToolkitError> = named_keys
         .map(|named_key| {
             named_key
                 .key()
@@ -31,3 +47,68 @@ pub fn extract_named_keys(
 
     Ok(named_keys)
 }
+
+/// Extracts versions of the contract package returned by the node.
+pub fn extract_contract_package(contract_package: ContractPackage) -> ContractPackageInfo {
+    let mut versions: Vec<ContractVersionInfo> = contract_package
+        .versions()
+        .map(|version| ContractVersionInfo {
+            protocol_version_major: version.protocol_version_major(),
+            contract_version: version.contract_version(),
+            contract_hash: version.contract_hash().value(),
+            enabled: !contract_package.disabled_versions().any(|disabled| {
+                disabled.protocol_version_major() == version.protocol_version_major()
+                    && disabled.contract_version() == version.contract_version()
+            }),
+        })
+        .collect();
+    versions.sort_by_key(|version| (version.protocol_version_major, version.contract_version));
+
+    ContractPackageInfo { versions }
+}
+
+/// Extracts versions of the contract package read from the global state.
+pub fn contract_package_info(
+    contract_package: &casper_types::ContractPackage,
+) -> ContractPackageInfo {
+    // Versions are kept in a map ordered by version key.
+    let versions = contract_package
+        .versions()
+        .iter()
+        .map(|(version_key, contract_hash)| ContractVersionInfo {
+            protocol_version_major: version_key.protocol_version_major(),
+            contract_version: version_key.contract_version(),
+            contract_hash: contract_hash.value(),
+            enabled: contract_package.is_version_enabled(*version_key),
+        })
+        .collect();
+
+    ContractPackageInfo { versions }
+}
+
+/// Validates block returned by the node - same as `casper_client::get_block` does.
+pub fn validate_block(
+    block_identifier: Option<BlockIdentifier>,
+    result: &GetBlockResult,
+) -> Result<(), ToolkitError> {
+    let block = match &result.block {
+        Some(block) => block,
+        None => return Ok(()),
+    };
+
+    // Block hash is computed differently since node 1.5.
+    if let Err(v1_error) = casper_client::types::validate_block_hashes_v1(block) {
+        casper_client::types::validate_block_hashes_v2(block)
+            .map_err(|_v2_error| casper_client::Error::from(v1_error))?;
+    }
+
+    match block_identifier {
+        Some(BlockIdentifier::Hash(block_hash)) if block_hash.inner() != block.hash().inner() => {
+            Err(casper_client::Error::from(ValidateResponseError::UnexpectedBlockHash).into())
+        }
+        Some(BlockIdentifier::Height(height)) if height != block.header().height() => {
+            Err(casper_client::Error::from(ValidateResponseError::UnexpectedBlockHeight).into())
+        }
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,95 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use crate::error::ToolkitError;
+use crate::utils::write_atomically;
+
+/// Last fully processed block height of every contract, keyed by the contract events URef.
+pub type Checkpoints = BTreeMap<String, u64>;
+
+/// Storage of the scanning progress.
+pub trait CheckpointStore {
+    fn load(&self) -> Result<Checkpoints, ToolkitError>;
+
+    fn save(&mut self, checkpoints: &Checkpoints) -> Result<(), ToolkitError>;
+}
+
+/// Checkpoints kept in memory - progress is lost on restart.
+#[derive(Debug, Default)]
+pub struct MemoryCheckpointStore {
+    checkpoints: Checkpoints,
+}
+
+impl CheckpointStore for MemoryCheckpointStore {
+    fn load(&self) -> Result<Checkpoints, ToolkitError> {
+        Ok(self.checkpoints.clone())
+    }
+
+    fn save(&mut self, checkpoints: &Checkpoints) -> Result<(), ToolkitError> {
+        self.checkpoints = checkpoints.clone();
+        Ok(())
+    }
+}
+
+/// Checkpoints persisted as a text file, with a line per contract.
+#[derive(Debug)]
+pub struct FileCheckpointStore {
+    path: PathBuf,
+}
+
+impl FileCheckpointStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+impl CheckpointStore for FileCheckpointStore {
+    fn load(&self) -> Result<Checkpoints, ToolkitError> {
+        let content = match std::fs::read_to_string(&self.path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Checkpoints::new()),
+            Err(e) => return Err(e.into()),
+        };
+
+        content
+            .lines()
+            .map(|line| {
+                let (key, block_height) = line.split_once(' ')?;
+                Some((key.to_string(), block_height.parse().ok()?))
+            })
+            .collect::<Option<Checkpoints>>()
+            .ok_or(ToolkitError::DeserializationError {
+                context: "checkpoint",
+            })
+    }
+
+    fn save(&mut self, checkpoints: &Checkpoints) -> Result<(), ToolkitError> {
+        let content: String = checkpoints
+            .iter()
+            .map(|(key, block_height)| format!("{} {}\n", key, block_height))
+            .collect();
+
+        write_atomically(&self.path, content)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_checkpoint_roundtrip() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("checkpoint");
+        let mut store = FileCheckpointStore::new(&path);
+        let mut checkpoints = Checkpoints::new();
+
+        assert_eq!(store.load().unwrap(), checkpoints);
+        checkpoints.insert("uref-01-007".to_string(), 42);
+        store.save(&checkpoints).unwrap();
+        checkpoints.insert("uref-01-007".to_string(), 43);
+        checkpoints.insert("uref-02-007".to_string(), 7);
+        store.save(&checkpoints).unwrap();
+        assert_eq!(FileCheckpointStore::new(&path).load().unwrap(), checkpoints);
+    }
+}
@@ -0,0 +1,372 @@
+use std::future::Future;
+use std::ops::Range;
+
+use casper_event_standard::Schemas;
+use casper_hashing::Digest;
+
+use crate::error::ToolkitError;
+use crate::event::ContextualEvent;
+use crate::fetcher::{
+    fetch_block_deploys, group_deploys_events, parse_deploys_events, BlockIdentifier, DeployEvents,
+    Fetcher,
+};
+use crate::metadata::CesMetadataRef;
+use crate::rpc::client::CasperClient;
+use crate::rpc::types::{BlockInfo, DeployExecution};
+use crate::source::GlobalStateSource;
+
+mod checkpoint;
+
+pub use checkpoint::{CheckpointStore, Checkpoints, FileCheckpointStore, MemoryCheckpointStore};
+
+/// Events of the registered contracts, emitted in a single block.
+#[derive(Debug)]
+pub struct BlockEvents {
+    pub block_height: u64,
+    pub deploys: Vec<DeployEvents>,
+}
+
+/// Contract which events could not be parsed in the block, stopping its scan there.
+#[derive(Debug)]
+pub struct StalledContract {
+    pub ces_metadata: CesMetadataRef,
+    pub block_height: u64,
+    pub error: ToolkitError,
+}
+
+/// Walks blocks in order and extracts events of the registered contracts from deploys
+/// execution results.
+///
+/// Progress of every contract is saved into the checkpoint store after every processed block,
+/// so the interrupted scan resumes exactly where it stopped. Contract registered after the
+/// previous scan is scanned from the start of the range, together with the others once it
+/// catches up.
+///
+/// Event missing in the registered schema, e.g. added by the contract upgrade, is parsed with
+/// the schema read at the block's state root, which replaces the registered one.
+pub struct Scanner<C: CheckpointStore, S = CasperClient> {
+    client: S,
+    contracts: Vec<(CesMetadataRef, Schemas)>,
+    checkpoint_store: C,
+}
+
+impl<C: CheckpointStore, S: GlobalStateSource> Scanner<C, S> {
+    pub fn new(client: S, checkpoint_store: C) -> Self {
+        Self {
+            client,
+            contracts: vec![],
+            checkpoint_store,
+        }
+    }
+
+    pub fn register_contract(&mut self, ces_metadata: CesMetadataRef, event_schema: Schemas) {
+        self.contracts.push((ces_metadata, event_schema));
+    }
+
+    pub fn checkpoint_store(&self) -> &C {
+        &self.checkpoint_store
+    }
+
+    /// Scans the given block heights range, skipping blocks that were already processed for
+    /// the contract.
+    ///
+    /// Handler is called for every block with at least one event. Block is considered processed
+    /// when the handler succeeds - any error stops the scan, leaving checkpoints at the last
+    /// processed block.
+    ///
+    /// Contract which events can not be parsed stops at that block, while the others continue.
+    /// Such contracts are returned, and are retried from that block by the next scan.
+    pub async fn scan<F, Fut>(
+        &mut self,
+        block_heights: Range<u64>,
+        mut handler: F,
+    ) -> Result<Vec<StalledContract>, ToolkitError>
+    where
+        F: FnMut(BlockEvents) -> Fut,
+        Fut: Future<Output = Result<(), ToolkitError>>,
+    {
+        // Resume every contract after its last processed block.
+        let mut checkpoints = self.checkpoint_store.load()?;
+        let next_heights: Vec<u64> = self
+            .contracts
+            .iter()
+            .map(
+                |(ces_metadata, _)| match checkpoints.get(&checkpoint_key(ces_metadata)) {
+                    Some(last_height) => block_heights.start.max(last_height + 1),
+                    None => block_heights.start,
+                },
+            )
+            .collect();
+        let start = next_heights
+            .iter()
+            .copied()
+            .min()
+            .unwrap_or(block_heights.end);
+
+        let mut stalled_contracts = vec![];
+        let mut stalled = vec![false; self.contracts.len()];
+        for block_height in start..block_heights.end {
+            if stalled.iter().all(|stalled| *stalled) {
+                break;
+            }
+            // Only contracts that did not process the block yet.
+            let indices: Vec<usize> = (0..self.contracts.len())
+                .filter(|index| !stalled[*index] && next_heights[*index] <= block_height)
+                .collect();
+
+            let block_identifier = BlockIdentifier::Height(block_height);
+            let (block, deploys) = fetch_block_deploys(&self.client, block_identifier).await?;
+
+            let mut contracts_events = vec![];
+            let mut processed = vec![];
+            for index in indices {
+                match self.parse_contract_events(index, &block, &deploys).await? {
+                    Ok(events) => {
+                        contracts_events.push(events);
+                        processed.push(index);
+                    }
+                    Err(error) => {
+                        stalled[index] = true;
+                        stalled_contracts.push(StalledContract {
+                            ces_metadata: self.contracts[index].0.clone(),
+                            block_height,
+                            error,
+                        });
+                    }
+                }
+            }
+
+            let deploys = group_deploys_events(&block, &deploys, contracts_events);
+            if !deploys.is_empty() {
+                handler(BlockEvents {
+                    block_height,
+                    deploys,
+                })
+                .await?;
+            }
+
+            for index in processed {
+                checkpoints.insert(checkpoint_key(&self.contracts[index].0), block_height);
+            }
+            self.checkpoint_store.save(&checkpoints)?;
+        }
+
+        Ok(stalled_contracts)
+    }
+
+    // Parses events of the contract, re-reading its schema at the block's state root if the
+    // event is missing in it. Outer error is the failed schema read, inner one the events that
+    // can not be parsed.
+    async fn parse_contract_events(
+        &mut self,
+        index: usize,
+        block: &BlockInfo,
+        deploys: &[(Digest, DeployExecution)],
+    ) -> Result<Result<Vec<Vec<ContextualEvent>>, ToolkitError>, ToolkitError> {
+        let (ces_metadata, event_schema) = &self.contracts[index];
+        match parse_deploys_events(deploys, ces_metadata, event_schema) {
+            Err(ToolkitError::MissingEventSchema(_)) => {}
+            result => return Ok(result),
+        }
+
+        let fetcher = Fetcher::new(&self.client, ces_metadata.clone());
+        let event_schema = fetcher
+            .at_state_root(block.state_root_hash)
+            .fetch_schema()
+            .await?;
+        let result = parse_deploys_events(deploys, ces_metadata, &event_schema);
+        self.contracts[index].1 = event_schema;
+
+        Ok(result)
+    }
+}
+
+// Events storage identifies the contract, even if its hash is not known. Access rights are
+// not part of the key, as they depend on the named keys the URef was read from.
+fn checkpoint_key(ces_metadata: &CesMetadataRef) -> String {
+    hex::encode(ces_metadata.events_data.addr())
+}
+
+#[cfg(test)]
+mod tests {
+    use casper_event_standard::casper_types::{
+        CLType, CLValue, ExecutionEffect, ExecutionResult, HashAddr, TransformEntry, U512,
+    };
+    use casper_event_standard::Schema;
+
+    use super::*;
+    use crate::event::Event;
+    use crate::source::MemorySource;
+    use crate::test_support::{event_transform, events_length_transform, test_event, test_schemas};
+
+    // Every block has a deploy emitting an event of both contracts.
+    fn source_with_blocks(blocks_count: u8) -> (MemorySource, CesMetadataRef, CesMetadataRef) {
+        let mut source = MemorySource::new();
+        let first = source.add_ces_contract([1; 32], &test_schemas()).unwrap();
+        let second = source.add_ces_contract([2; 32], &test_schemas()).unwrap();
+        for height in 0..blocks_count {
+            let event = test_event(u64::from(height));
+            let transforms = [&first, &second]
+                .into_iter()
+                .flat_map(|ces_metadata| emitted_event(ces_metadata, height, &event))
+                .collect();
+            put_block(&mut source, height, transforms);
+        }
+        (source, first, second)
+    }
+
+    // Transforms of the event emitted as the contract's event number `id`.
+    fn emitted_event(ces_metadata: &CesMetadataRef, id: u8, event: &Event) -> Vec<TransformEntry> {
+        vec![
+            event_transform(ces_metadata, u32::from(id), event).unwrap(),
+            events_length_transform(ces_metadata, u32::from(id) + 1).unwrap(),
+        ]
+    }
+
+    // Adds block with a single deploy, writing the transforms.
+    fn put_block(source: &mut MemorySource, height: u8, transforms: Vec<TransformEntry>) {
+        let block_hash = Digest::from([height; 32]);
+        let deploy_hash = Digest::from([100 + height; 32]);
+        let execution_result = ExecutionResult::Success {
+            effect: ExecutionEffect {
+                operations: vec![],
+                transforms,
+            },
+            transfers: vec![],
+            cost: U512::zero(),
+        };
+        source.put_deploy(
+            deploy_hash,
+            DeployExecution {
+                block_hash,
+                execution_result,
+            },
+        );
+        source.put_block(BlockInfo {
+            hash: block_hash,
+            height: u64::from(height),
+            timestamp: 0,
+            state_root_hash: Digest::default(),
+            deploy_hashes: vec![deploy_hash],
+        });
+    }
+
+    // Scans the range, returning emitting contracts of the events in every block, and the
+    // stalled contracts.
+    async fn scan_contracts<C: CheckpointStore>(
+        scanner: &mut Scanner<C, MemorySource>,
+        block_heights: Range<u64>,
+    ) -> (Vec<(u64, Vec<HashAddr>)>, Vec<StalledContract>) {
+        let mut scanned = vec![];
+        let stalled = scanner
+            .scan(block_heights, |block_events| {
+                let contract_hashes = block_events
+                    .deploys
+                    .iter()
+                    .flat_map(|deploy| &deploy.events)
+                    .filter_map(|event| event.context.contract_hash)
+                    .collect();
+                scanned.push((block_events.block_height, contract_hashes));
+                async { Ok(()) }
+            })
+            .await
+            .unwrap();
+        (scanned, stalled)
+    }
+
+    #[tokio::test]
+    async fn test_scan_contract_registered_later() {
+        let (source, first, second) = source_with_blocks(3);
+        let mut scanner = Scanner::new(source, MemoryCheckpointStore::default());
+        scanner.register_contract(first, test_schemas());
+
+        let (scanned, _) = scan_contracts(&mut scanner, 0..2).await;
+        assert_eq!(scanned, vec![(0, vec![[1; 32]]), (1, vec![[1; 32]])]);
+
+        // New contract catches up, without repeating events of the first one.
+        scanner.register_contract(second, test_schemas());
+        let (scanned, stalled) = scan_contracts(&mut scanner, 0..3).await;
+        assert_eq!(
+            scanned,
+            vec![
+                (0, vec![[2; 32]]),
+                (1, vec![[2; 32]]),
+                (2, vec![[1; 32], [2; 32]])
+            ]
+        );
+        assert!(stalled.is_empty());
+        let checkpoints = scanner.checkpoint_store().load().unwrap();
+        assert_eq!(
+            checkpoints.values().copied().collect::<Vec<_>>(),
+            vec![2, 2]
+        );
+
+        // Nothing left to scan.
+        assert!(scan_contracts(&mut scanner, 0..3).await.0.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_scan_upgraded_schema_and_stalled_contract() {
+        let (mut source, first, second) = source_with_blocks(2);
+
+        // First contract is upgraded with a new event, second one emits an unknown event.
+        let mut upgraded_schemas = test_schemas();
+        let mut schema = Schema::new();
+        schema.with_elem("value", CLType::U64);
+        upgraded_schemas.0.insert("Upgraded".to_string(), schema);
+        source.put_clvalue(
+            &first.events_schema,
+            CLValue::from_t(upgraded_schemas).unwrap(),
+        );
+        let upgraded_event = Event {
+            name: "Upgraded".to_string(),
+            ..test_event(2)
+        };
+        let unknown_event = Event {
+            name: "Unknown".to_string(),
+            ..test_event(2)
+        };
+        let mut transforms = emitted_event(&first, 2, &upgraded_event);
+        transforms.extend(emitted_event(&second, 2, &unknown_event));
+        put_block(&mut source, 2, transforms);
+        let event = test_event(3);
+        let mut transforms = emitted_event(&first, 3, &event);
+        transforms.extend(emitted_event(&second, 3, &event));
+        put_block(&mut source, 3, transforms);
+
+        let mut scanner = Scanner::new(source, MemoryCheckpointStore::default());
+        scanner.register_contract(first, test_schemas());
+        scanner.register_contract(second.clone(), test_schemas());
+
+        // Second contract stops at the unknown event, not blocking the first one.
+        let (scanned, stalled) = scan_contracts(&mut scanner, 0..4).await;
+        assert_eq!(
+            scanned,
+            vec![
+                (0, vec![[1; 32], [2; 32]]),
+                (1, vec![[1; 32], [2; 32]]),
+                (2, vec![[1; 32]]),
+                (3, vec![[1; 32]])
+            ]
+        );
+        assert_eq!(stalled.len(), 1);
+        assert_eq!(stalled[0].ces_metadata.contract_hash, Some([2; 32]));
+        assert_eq!(stalled[0].block_height, 2);
+        assert!(matches!(
+            &stalled[0].error,
+            ToolkitError::MissingEventSchema(name) if name == "Unknown"
+        ));
+
+        // Checkpoint is keyed by the events URef address, without access rights.
+        let checkpoints = scanner.checkpoint_store().load().unwrap();
+        assert_eq!(
+            checkpoints.get(&hex::encode(second.events_data.addr())),
+            Some(&1)
+        );
+
+        // Stalled contract is retried from the failed block.
+        let (scanned, stalled) = scan_contracts(&mut scanner, 0..4).await;
+        assert!(scanned.is_empty());
+        assert_eq!(stalled[0].block_height, 2);
+    }
+}
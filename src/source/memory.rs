@@ -0,0 +1,386 @@
+use std::collections::BTreeMap;
+
+use async_trait::async_trait;
+use casper_client::rpcs::common::BlockIdentifier;
+use casper_event_standard::casper_types;
+use casper_event_standard::{Schemas, CES_VERSION};
+use casper_hashing::Digest;
+use casper_types::account::AccountHash;
+use casper_types::bytesrepr::Bytes;
+use casper_types::contracts::NamedKeys;
+#[cfg(any(test, feature = "test-support"))]
+use casper_types::{bytesrepr::ToBytes, CLType, StoredValue};
+use casper_types::{AccessRights, CLValue, HashAddr, Key, URef, URefAddr};
+
+use crate::error::ToolkitError;
+use crate::event::Event;
+use crate::metadata::{
+    CesMetadataRef, EVENTS_CES_VERSION_KEY, EVENTS_DATA_KEY, EVENTS_LENGTH_KEY, EVENTS_SCHEMA_KEY,
+};
+use crate::rpc::compat;
+use crate::rpc::types::{BlockInfo, ContractPackageInfo, DeployExecution};
+
+use super::GlobalStateSource;
+
+/// Global state kept in memory, for testing without a node.
+///
+/// It holds a single global state, so the state root hash given to the reads is ignored.
+#[derive(Default)]
+pub struct MemorySource {
+    state_root_hash: Digest,
+    named_keys: BTreeMap<HashAddr, NamedKeys>,
+    account_named_keys: BTreeMap<AccountHash, NamedKeys>,
+    contract_packages: BTreeMap<HashAddr, ContractPackageInfo>,
+    values: BTreeMap<URefAddr, CLValue>,
+    dictionaries: BTreeMap<(URefAddr, String), CLValue>,
+    deploys: BTreeMap<Digest, DeployExecution>,
+    blocks: Vec<BlockInfo>,
+    // Counter used for generating URefs of registered contracts.
+    last_uref_id: u64,
+}
+
+impl MemorySource {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets state root hash returned for every block.
+    pub fn set_state_root_hash(&mut self, state_root_hash: Digest) {
+        self.state_root_hash = state_root_hash;
+    }
+
+    pub fn put_named_keys(&mut self, contract_hash: HashAddr, named_keys: NamedKeys) {
+        self.named_keys.insert(contract_hash, named_keys);
+    }
+
+    pub fn put_account_named_keys(&mut self, account_hash: AccountHash, named_keys: NamedKeys) {
+        self.account_named_keys.insert(account_hash, named_keys);
+    }
+
+    pub fn put_contract_package(
+        &mut self,
+        contract_package_hash: HashAddr,
+        contract_package: ContractPackageInfo,
+    ) {
+        self.contract_packages
+            .insert(contract_package_hash, contract_package);
+    }
+
+    pub fn put_clvalue(&mut self, uref: &URef, clvalue: CLValue) {
+        self.values.insert(uref.addr(), clvalue);
+    }
+
+    pub fn put_dictionary_item(&mut self, seed_uref: &URef, item_key: &str, clvalue: CLValue) {
+        self.dictionaries
+            .insert((seed_uref.addr(), item_key.to_string()), clvalue);
+    }
+
+    pub fn put_deploy(&mut self, deploy_hash: Digest, deploy_execution: DeployExecution) {
+        self.deploys.insert(deploy_hash, deploy_execution);
+    }
+
+    pub fn put_block(&mut self, block: BlockInfo) {
+        self.blocks.push(block);
+    }
+
+    /// Returns the block with the highest height.
+    pub fn latest_block(&self) -> Option<&BlockInfo> {
+        self.blocks.iter().max_by_key(|block| block.height)
+    }
+
+    /// Registers CES contract with the given schema and no events.
+    ///
+    /// Returned metadata can be used directly, or fetched again with
+    /// `CesMetadataRef::fetch_metadata`.
+    pub fn add_ces_contract(
+        &mut self,
+        contract_hash: HashAddr,
+        schemas: &Schemas,
+    ) -> Result<CesMetadataRef, ToolkitError> {
+        let ces_metadata = CesMetadataRef {
+            contract_hash: Some(contract_hash),
+            events_schema: self.next_uref(),
+            events_length: self.next_uref(),
+            events_data: self.next_uref(),
+            ces_version: Some(CES_VERSION.to_string()),
+        };
+        let ces_version_uref = self.next_uref();
+
+        let mut named_keys = NamedKeys::new();
+        named_keys.insert(
+            EVENTS_SCHEMA_KEY.to_string(),
+            Key::URef(ces_metadata.events_schema),
+        );
+        named_keys.insert(
+            EVENTS_LENGTH_KEY.to_string(),
+            Key::URef(ces_metadata.events_length),
+        );
+        named_keys.insert(
+            EVENTS_DATA_KEY.to_string(),
+            Key::URef(ces_metadata.events_data),
+        );
+        named_keys.insert(
+            EVENTS_CES_VERSION_KEY.to_string(),
+            Key::URef(ces_version_uref),
+        );
+        self.put_named_keys(contract_hash, named_keys);
+
+        let schemas = CLValue::from_t(schemas.clone())
+            .map_err(|e| ToolkitError::InvalidCLValue(e.to_string()))?;
+        self.put_clvalue(&ces_metadata.events_schema, schemas);
+        let ces_version = CLValue::from_t(CES_VERSION.to_string())
+            .map_err(|e| ToolkitError::InvalidCLValue(e.to_string()))?;
+        self.put_clvalue(&ces_version_uref, ces_version);
+        self.set_events_count(&ces_metadata, 0)?;
+
+        Ok(ces_metadata)
+    }
+
+    pub fn set_events_count(
+        &mut self,
+        ces_metadata: &CesMetadataRef,
+        events_count: u32,
+    ) -> Result<(), ToolkitError> {
+        let events_count = CLValue::from_t(events_count)
+            .map_err(|e| ToolkitError::InvalidCLValue(e.to_string()))?;
+        self.put_clvalue(&ces_metadata.events_length, events_count);
+
+        Ok(())
+    }
+
+    /// Stores event bytes, as produced by `Event::to_ces_bytes`, under the given ID.
+    ///
+    /// Events count is not changed.
+    pub fn put_event_bytes(
+        &mut self,
+        ces_metadata: &CesMetadataRef,
+        id: u32,
+        event_bytes: Vec<u8>,
+    ) -> Result<(), ToolkitError> {
+        let event_value = CLValue::from_t(Bytes::from(event_bytes))
+            .map_err(|e| ToolkitError::InvalidCLValue(e.to_string()))?;
+        self.put_dictionary_item(&ces_metadata.events_data, &id.to_string(), event_value);
+
+        Ok(())
+    }
+
+    /// Appends event to the contract events, returning its ID.
+    pub fn push_event(
+        &mut self,
+        ces_metadata: &CesMetadataRef,
+        event: &Event,
+    ) -> Result<u32, ToolkitError> {
+        let id: u32 = self
+            .values
+            .get(&ces_metadata.events_length.addr())
+            .cloned()
+            .map(|events_count| events_count.into_t())
+            .transpose()
+            .map_err(|e| ToolkitError::InvalidCLValue(e.to_string()))?
+            .unwrap_or_default();
+
+        self.put_event_bytes(ces_metadata, id, event.to_ces_bytes()?)?;
+        self.set_events_count(ces_metadata, id + 1)?;
+
+        Ok(id)
+    }
+
+    /// Returns URef values and dictionary items, keyed and wrapped as in the global state.
+    #[cfg(any(test, feature = "test-support"))]
+    pub(crate) fn stored_values(&self) -> Result<Vec<(Key, StoredValue)>, ToolkitError> {
+        let mut stored_values = vec![];
+        for (uref_addr, clvalue) in &self.values {
+            let key = Key::URef(URef::new(*uref_addr, AccessRights::NONE));
+            stored_values.push((key, StoredValue::CLValue(clvalue.clone())));
+        }
+        // Node stores dictionary value together with its seed URef and item key.
+        for ((seed_uref_addr, item_key), clvalue) in &self.dictionaries {
+            let seed_uref = URef::new(*seed_uref_addr, AccessRights::NONE);
+            let mut dictionary_value =
+                clvalue
+                    .to_bytes()
+                    .map_err(|_e| ToolkitError::SerializationError {
+                        context: "dictionary value",
+                    })?;
+            for bytes in [seed_uref_addr.to_vec(), item_key.as_bytes().to_vec()] {
+                dictionary_value.extend(Bytes::from(bytes).to_bytes().map_err(|_e| {
+                    ToolkitError::SerializationError {
+                        context: "dictionary value",
+                    }
+                })?);
+            }
+            let clvalue = CLValue::from_components(CLType::Any, dictionary_value);
+            let key = Key::dictionary(seed_uref, item_key.as_bytes());
+            stored_values.push((key, StoredValue::CLValue(clvalue)));
+        }
+
+        Ok(stored_values)
+    }
+
+    fn next_uref(&mut self) -> URef {
+        self.last_uref_id += 1;
+        let mut addr = URefAddr::default();
+        addr[..8].copy_from_slice(&self.last_uref_id.to_be_bytes());
+
+        URef::new(addr, AccessRights::READ_ADD_WRITE)
+    }
+
+    fn find_block(&self, block_identifier: &BlockIdentifier) -> Result<&BlockInfo, ToolkitError> {
+        let block = match block_identifier {
+            BlockIdentifier::Hash(block_hash) => {
+                let block_hash = compat::digest_from_client_types(&block_hash.inner())?;
+                self.blocks.iter().find(|block| block.hash == block_hash)
+            }
+            BlockIdentifier::Height(height) => {
+                self.blocks.iter().find(|block| block.height == *height)
+            }
+        };
+
+        block.ok_or(ToolkitError::MissingBlock)
+    }
+}
+
+#[async_trait]
+impl GlobalStateSource for MemorySource {
+    async fn get_state_root_hash(
+        &self,
+        _block_identifier: Option<BlockIdentifier>,
+    ) -> Result<Digest, ToolkitError> {
+        Ok(self.state_root_hash)
+    }
+
+    async fn get_contract_named_keys(
+        &self,
+        _state_root_hash: Digest,
+        contract_hash: HashAddr,
+    ) -> Result<NamedKeys, ToolkitError> {
+        self.named_keys.get(&contract_hash).cloned().ok_or_else(|| {
+            ToolkitError::MissingStoredValue {
+                context: format!("contract {}", hex::encode(contract_hash)),
+            }
+        })
+    }
+
+    async fn get_account_named_keys(
+        &self,
+        _state_root_hash: Digest,
+        account_hash: AccountHash,
+    ) -> Result<NamedKeys, ToolkitError> {
+        self.account_named_keys
+            .get(&account_hash)
+            .cloned()
+            .ok_or_else(|| ToolkitError::MissingStoredValue {
+                context: account_hash.to_formatted_string(),
+            })
+    }
+
+    async fn get_contract_package(
+        &self,
+        _state_root_hash: Digest,
+        contract_package_hash: HashAddr,
+    ) -> Result<ContractPackageInfo, ToolkitError> {
+        self.contract_packages
+            .get(&contract_package_hash)
+            .cloned()
+            .ok_or_else(|| ToolkitError::MissingStoredValue {
+                context: format!("contract package {}", hex::encode(contract_package_hash)),
+            })
+    }
+
+    async fn get_stored_clvalue(
+        &self,
+        _state_root_hash: Digest,
+        uref: &URef,
+    ) -> Result<CLValue, ToolkitError> {
+        self.values
+            .get(&uref.addr())
+            .cloned()
+            .ok_or_else(|| ToolkitError::MissingStoredValue {
+                context: uref.to_formatted_string(),
+            })
+    }
+
+    async fn get_stored_clvalue_from_dict(
+        &self,
+        _state_root_hash: Digest,
+        dictionary_seed_uref: &URef,
+        dictionary_item_key: &str,
+    ) -> Result<CLValue, ToolkitError> {
+        let item = (dictionary_seed_uref.addr(), dictionary_item_key.to_string());
+
+        self.dictionaries
+            .get(&item)
+            .cloned()
+            .ok_or_else(|| ToolkitError::MissingStoredValue {
+                context: format!(
+                    "dictionary item '{}' of {}",
+                    dictionary_item_key,
+                    dictionary_seed_uref.to_formatted_string()
+                ),
+            })
+    }
+
+    async fn get_deploy_result(
+        &self,
+        deploy_hash: Digest,
+    ) -> Result<DeployExecution, ToolkitError> {
+        self.deploys
+            .get(&deploy_hash)
+            .cloned()
+            .ok_or(ToolkitError::MissingDeploy)
+    }
+
+    async fn get_block(
+        &self,
+        block_identifier: BlockIdentifier,
+    ) -> Result<BlockInfo, ToolkitError> {
+        self.find_block(&block_identifier).cloned()
+    }
+
+    async fn get_latest_block(&self) -> Result<BlockInfo, ToolkitError> {
+        self.latest_block()
+            .cloned()
+            .ok_or(ToolkitError::MissingBlock)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fetcher::Fetcher;
+    use crate::test_support::{test_event, test_schemas};
+
+    #[tokio::test]
+    async fn test_fetcher_reads_memory_source() {
+        let contract_hash = [1; 32];
+        let mut source = MemorySource::new();
+        let ces_metadata = source
+            .add_ces_contract(contract_hash, &test_schemas())
+            .unwrap();
+        for value in [10u64, 20, 30] {
+            source
+                .push_event(&ces_metadata, &test_event(value))
+                .unwrap();
+        }
+
+        let ces_metadata = CesMetadataRef::fetch_metadata(&source, &hex::encode(contract_hash))
+            .await
+            .unwrap();
+        assert_eq!(ces_metadata.ces_version.as_deref(), Some(CES_VERSION));
+        let fetcher = Fetcher::new(source, ces_metadata);
+
+        let schemas = fetcher.fetch_schema().await.unwrap();
+        assert_eq!(fetcher.fetch_events_count().await.unwrap(), 3);
+
+        let events = fetcher.fetch_events(0..4, &schemas).await.unwrap();
+        let values: Vec<Option<u64>> = events
+            .into_iter()
+            .map(|(_id, event)| {
+                event
+                    .ok()
+                    .map(|event| event.event.fields[0].1.clone().into_t().unwrap())
+            })
+            .collect();
+        assert_eq!(values, vec![Some(10), Some(20), Some(30), None]);
+    }
+}
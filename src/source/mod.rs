@@ -0,0 +1,227 @@
+use async_trait::async_trait;
+use casper_client::rpcs::common::BlockIdentifier;
+use casper_event_standard::casper_types;
+use casper_hashing::Digest;
+use casper_types::account::AccountHash;
+use casper_types::contracts::NamedKeys;
+use casper_types::{CLValue, HashAddr, URef};
+
+use crate::error::ToolkitError;
+use crate::rpc::types::{BlockInfo, ContractPackageInfo, DeployExecution};
+
+mod memory;
+
+pub use memory::MemorySource;
+
+/// Source of the blockchain data used by the toolkit.
+///
+/// Default implementation is `CasperClient`, which talks to the node over JSON-RPC.
+#[async_trait]
+pub trait GlobalStateSource: Send + Sync {
+    /// Fetches state root hash - no block given means the latest available.
+    async fn get_state_root_hash(
+        &self,
+        block_identifier: Option<BlockIdentifier>,
+    ) -> Result<Digest, ToolkitError>;
+
+    /// Fetches named keys of the contract stored at given hash.
+    async fn get_contract_named_keys(
+        &self,
+        state_root_hash: Digest,
+        contract_hash: HashAddr,
+    ) -> Result<NamedKeys, ToolkitError>;
+
+    /// Fetches named keys of the account. Not supported by default.
+    async fn get_account_named_keys(
+        &self,
+        _state_root_hash: Digest,
+        _account_hash: AccountHash,
+    ) -> Result<NamedKeys, ToolkitError> {
+        Err(ToolkitError::UnsupportedOperation {
+            operation: "get_account_named_keys",
+        })
+    }
+
+    /// Fetches versions of the contract package stored at given hash. Not supported by default.
+    async fn get_contract_package(
+        &self,
+        _state_root_hash: Digest,
+        _contract_package_hash: HashAddr,
+    ) -> Result<ContractPackageInfo, ToolkitError> {
+        Err(ToolkitError::UnsupportedOperation {
+            operation: "get_contract_package",
+        })
+    }
+
+    /// Fetches value stored under given URef.
+    async fn get_stored_clvalue(
+        &self,
+        state_root_hash: Digest,
+        uref: &URef,
+    ) -> Result<CLValue, ToolkitError>;
+
+    /// Fetches dictionary item value.
+    async fn get_stored_clvalue_from_dict(
+        &self,
+        state_root_hash: Digest,
+        dictionary_seed_uref: &URef,
+        dictionary_item_key: &str,
+    ) -> Result<CLValue, ToolkitError>;
+
+    /// Maximal number of dictionary items read at once by `get_stored_clvalues_from_dict`,
+    /// 1 meaning items are read one by one.
+    fn max_batch_size(&self) -> usize {
+        1
+    }
+
+    /// Fetches multiple items of the same dictionary, in order of the given keys.
+    ///
+    /// Error is returned if reading all the items failed at once, e.g. because of rejected
+    /// batch request.
+    async fn get_stored_clvalues_from_dict(
+        &self,
+        state_root_hash: Digest,
+        dictionary_seed_uref: &URef,
+        dictionary_item_keys: &[String],
+    ) -> Result<Vec<Result<CLValue, ToolkitError>>, ToolkitError> {
+        let mut values = vec![];
+        for dictionary_item_key in dictionary_item_keys {
+            let value = self
+                .get_stored_clvalue_from_dict(
+                    state_root_hash,
+                    dictionary_seed_uref,
+                    dictionary_item_key,
+                )
+                .await;
+            values.push(value);
+        }
+
+        Ok(values)
+    }
+
+    /// Fetches execution result of the deploy.
+    async fn get_deploy_result(&self, deploy_hash: Digest)
+        -> Result<DeployExecution, ToolkitError>;
+
+    /// Fetches block with the hashes of included deploys.
+    async fn get_block(&self, block_identifier: BlockIdentifier)
+        -> Result<BlockInfo, ToolkitError>;
+
+    /// Fetches the latest block. Not supported by default.
+    async fn get_latest_block(&self) -> Result<BlockInfo, ToolkitError> {
+        Err(ToolkitError::UnsupportedOperation {
+            operation: "get_latest_block",
+        })
+    }
+
+    /// Fetches height of the oldest block available, e.g. synced by the node. By default the
+    /// whole history is available.
+    async fn get_lowest_available_block_height(&self) -> Result<u64, ToolkitError> {
+        Ok(0)
+    }
+}
+
+// Lets multiple fetchers share one source.
+#[async_trait]
+impl<T: GlobalStateSource + ?Sized> GlobalStateSource for &T {
+    async fn get_state_root_hash(
+        &self,
+        block_identifier: Option<BlockIdentifier>,
+    ) -> Result<Digest, ToolkitError> {
+        (**self).get_state_root_hash(block_identifier).await
+    }
+
+    async fn get_contract_named_keys(
+        &self,
+        state_root_hash: Digest,
+        contract_hash: HashAddr,
+    ) -> Result<NamedKeys, ToolkitError> {
+        (**self)
+            .get_contract_named_keys(state_root_hash, contract_hash)
+            .await
+    }
+
+    async fn get_account_named_keys(
+        &self,
+        state_root_hash: Digest,
+        account_hash: AccountHash,
+    ) -> Result<NamedKeys, ToolkitError> {
+        (**self)
+            .get_account_named_keys(state_root_hash, account_hash)
+            .await
+    }
+
+    async fn get_contract_package(
+        &self,
+        state_root_hash: Digest,
+        contract_package_hash: HashAddr,
+    ) -> Result<ContractPackageInfo, ToolkitError> {
+        (**self)
+            .get_contract_package(state_root_hash, contract_package_hash)
+            .await
+    }
+
+    async fn get_stored_clvalue(
+        &self,
+        state_root_hash: Digest,
+        uref: &URef,
+    ) -> Result<CLValue, ToolkitError> {
+        (**self).get_stored_clvalue(state_root_hash, uref).await
+    }
+
+    async fn get_stored_clvalue_from_dict(
+        &self,
+        state_root_hash: Digest,
+        dictionary_seed_uref: &URef,
+        dictionary_item_key: &str,
+    ) -> Result<CLValue, ToolkitError> {
+        (**self)
+            .get_stored_clvalue_from_dict(
+                state_root_hash,
+                dictionary_seed_uref,
+                dictionary_item_key,
+            )
+            .await
+    }
+
+    fn max_batch_size(&self) -> usize {
+        (**self).max_batch_size()
+    }
+
+    async fn get_stored_clvalues_from_dict(
+        &self,
+        state_root_hash: Digest,
+        dictionary_seed_uref: &URef,
+        dictionary_item_keys: &[String],
+    ) -> Result<Vec<Result<CLValue, ToolkitError>>, ToolkitError> {
+        (**self)
+            .get_stored_clvalues_from_dict(
+                state_root_hash,
+                dictionary_seed_uref,
+                dictionary_item_keys,
+            )
+            .await
+    }
+
+    async fn get_deploy_result(
+        &self,
+        deploy_hash: Digest,
+    ) -> Result<DeployExecution, ToolkitError> {
+        (**self).get_deploy_result(deploy_hash).await
+    }
+
+    async fn get_block(
+        &self,
+        block_identifier: BlockIdentifier,
+    ) -> Result<BlockInfo, ToolkitError> {
+        (**self).get_block(block_identifier).await
+    }
+
+    async fn get_latest_block(&self) -> Result<BlockInfo, ToolkitError> {
+        (**self).get_latest_block().await
+    }
+
+    async fn get_lowest_available_block_height(&self) -> Result<u64, ToolkitError> {
+        (**self).get_lowest_available_block_height().await
+    }
+}
@@ -0,0 +1,369 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use casper_event_standard::casper_types;
+use casper_event_standard::Schemas;
+use casper_hashing::Digest;
+use futures::stream::{self, BoxStream, Stream, StreamExt};
+use serde::Deserialize;
+
+use crate::error::ToolkitError;
+use crate::event::ContextualEvent;
+use crate::metadata::CesMetadataRef;
+use crate::parser::parse_events_from_transforms;
+
+/// Default delay before reconnecting to the SSE endpoint.
+pub const DEFAULT_RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// Events emitted by a single deploy, received from the node's SSE stream.
+#[derive(Debug)]
+pub struct DeployProcessedEvents {
+    /// SSE event ID - can be used for resuming the stream.
+    pub sse_event_id: Option<u64>,
+    pub deploy_hash: Digest,
+    pub block_hash: Digest,
+    pub events: Vec<ContextualEvent>,
+}
+
+/// Listener of the node's SSE endpoint e.g. `http://localhost:9999/events/main`.
+pub struct EventListener {
+    sse_endpoint: String,
+    reconnect_delay: Duration,
+    http_client: reqwest::Client,
+}
+
+impl EventListener {
+    pub fn new(sse_endpoint: &str) -> Self {
+        Self {
+            sse_endpoint: sse_endpoint.to_string(),
+            reconnect_delay: DEFAULT_RECONNECT_DELAY,
+            http_client: reqwest::Client::new(),
+        }
+    }
+
+    pub fn with_reconnect_delay(mut self, reconnect_delay: Duration) -> Self {
+        self.reconnect_delay = reconnect_delay;
+        self
+    }
+
+    /// Streams events of the given contract from processed deploys in real time.
+    ///
+    /// Passing `start_from` replays the node's buffered SSE events, starting with the given SSE
+    /// event ID. Failed connection is reported with an error, while connection closed by the
+    /// node is not. In both cases the listener reconnects after the reconnect delay, resuming
+    /// right after the last received SSE event.
+    pub fn listen<'a>(
+        &'a self,
+        ces_metadata: &'a CesMetadataRef,
+        event_schema: &'a Schemas,
+        start_from: Option<u64>,
+    ) -> impl Stream<Item = Result<DeployProcessedEvents, ToolkitError>> + 'a {
+        let state = ListenerState {
+            start_from,
+            connection: None,
+            parser: SseParser::default(),
+            pending: VecDeque::new(),
+            backoff: false,
+        };
+
+        stream::unfold(state, move |mut state| async move {
+            loop {
+                // Emit already parsed events.
+                if let Some(item) = state.pending.pop_front() {
+                    return Some((item, state));
+                }
+
+                // Wait before reconnecting.
+                if state.backoff {
+                    tokio::time::sleep(self.reconnect_delay).await;
+                    state.backoff = false;
+                }
+
+                let Some(connection) = state.connection.as_mut() else {
+                    match self.connect(state.start_from).await {
+                        Ok(connection) => {
+                            state.connection = Some(connection);
+                            state.parser = SseParser::default();
+                            continue;
+                        }
+                        Err(e) => {
+                            state.backoff = true;
+                            return Some((Err(e), state));
+                        }
+                    }
+                };
+
+                let chunk = match connection.next().await {
+                    Some(Ok(chunk)) => chunk,
+                    Some(Err(e)) => {
+                        state.connection = None;
+                        state.backoff = true;
+                        return Some((Err(e.into()), state));
+                    }
+                    None => {
+                        state.connection = None;
+                        state.backoff = true;
+                        continue;
+                    }
+                };
+
+                for message in state.parser.push(&chunk) {
+                    // Resume after the last received event.
+                    if let Some(id) = message.id {
+                        state.start_from = Some(id + 1);
+                    }
+
+                    match parse_deploy_processed(&message, ces_metadata, event_schema) {
+                        Ok(Some(deploy_events)) if !deploy_events.events.is_empty() => {
+                            state.pending.push_back(Ok(deploy_events));
+                        }
+                        Ok(_) => {}
+                        Err(e) => state.pending.push_back(Err(e)),
+                    }
+                }
+            }
+        })
+    }
+
+    async fn connect(
+        &self,
+        start_from: Option<u64>,
+    ) -> Result<BoxStream<'static, Result<Vec<u8>, reqwest::Error>>, ToolkitError> {
+        let mut request = self.http_client.get(&self.sse_endpoint);
+        if let Some(start_from) = start_from {
+            request = request.query(&[("start_from", start_from)]);
+        }
+
+        let response = request.send().await?.error_for_status()?;
+        let connection = response
+            .bytes_stream()
+            .map(|chunk| chunk.map(|bytes| bytes.to_vec()))
+            .boxed();
+
+        Ok(connection)
+    }
+}
+
+// Progress of `EventListener::listen`.
+struct ListenerState {
+    start_from: Option<u64>,
+    connection: Option<BoxStream<'static, Result<Vec<u8>, reqwest::Error>>>,
+    parser: SseParser,
+    pending: VecDeque<Result<DeployProcessedEvents, ToolkitError>>,
+    // Whether to wait for reconnect delay before next connection.
+    backoff: bool,
+}
+
+#[derive(Deserialize)]
+struct DeployProcessed {
+    deploy_hash: Digest,
+    block_hash: Digest,
+    execution_result: casper_types::ExecutionResult,
+}
+
+// Extract events from the `DeployProcessed` message - other messages are ignored.
+fn parse_deploy_processed(
+    message: &SseMessage,
+    ces_metadata: &CesMetadataRef,
+    event_schema: &Schemas,
+) -> Result<Option<DeployProcessedEvents>, ToolkitError> {
+    let mut value: serde_json::Value =
+        serde_json::from_str(&message.data).map_err(|_e| ToolkitError::DeserializationError {
+            context: "SSE message",
+        })?;
+    let Some(deploy_processed) = value.get_mut("DeployProcessed") else {
+        return Ok(None);
+    };
+    let deploy_processed: DeployProcessed = serde_json::from_value(deploy_processed.take())
+        .map_err(|_e| ToolkitError::DeserializationError {
+            context: "DeployProcessed",
+        })?;
+
+    let mut events = match deploy_processed.execution_result {
+        casper_types::ExecutionResult::Failure { .. } => vec![],
+        casper_types::ExecutionResult::Success { effect, .. } => {
+            parse_events_from_transforms(&effect.transforms, ces_metadata, event_schema)?
+        }
+    };
+
+    // Block height and timestamp are not part of the message.
+    for ContextualEvent { context, .. } in events.iter_mut() {
+        context.deploy_hash = Some(deploy_processed.deploy_hash);
+        context.block_hash = Some(deploy_processed.block_hash);
+    }
+
+    Ok(Some(DeployProcessedEvents {
+        sse_event_id: message.id,
+        deploy_hash: deploy_processed.deploy_hash,
+        block_hash: deploy_processed.block_hash,
+        events,
+    }))
+}
+
+struct SseMessage {
+    id: Option<u64>,
+    data: String,
+}
+
+// Incremental parser of the `text/event-stream` format.
+#[derive(Default)]
+struct SseParser {
+    buffer: Vec<u8>,
+    id: Option<u64>,
+    data: String,
+}
+
+impl SseParser {
+    fn push(&mut self, chunk: &[u8]) -> Vec<SseMessage> {
+        self.buffer.extend_from_slice(chunk);
+
+        let mut messages = vec![];
+        while let Some(position) = self.buffer.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=position).collect();
+            let line = String::from_utf8_lossy(&line);
+            let line = line.trim_end_matches(['\n', '\r']);
+
+            // Empty line dispatches the message.
+            if line.is_empty() {
+                if !self.data.is_empty() {
+                    messages.push(SseMessage {
+                        id: self.id.take(),
+                        data: std::mem::take(&mut self.data),
+                    });
+                }
+                continue;
+            }
+
+            let (field, value) = match line.split_once(':') {
+                Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+                None => (line, ""),
+            };
+            match field {
+                "data" => {
+                    if !self.data.is_empty() {
+                        self.data.push('\n');
+                    }
+                    self.data.push_str(value);
+                }
+                "id" => self.id = value.parse().ok(),
+                // Comments and unsupported fields.
+                _ => {}
+            }
+        }
+
+        messages
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use casper_types::{AccessRights, ExecutionEffect, ExecutionResult, URef, U512};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    use super::*;
+    use crate::test_support::{event_transform, events_length_transform, test_event, test_schemas};
+
+    fn test_metadata() -> CesMetadataRef {
+        CesMetadataRef {
+            contract_hash: None,
+            events_schema: URef::new([1; 32], AccessRights::READ_ADD_WRITE),
+            events_length: URef::new([2; 32], AccessRights::READ_ADD_WRITE),
+            events_data: URef::new([3; 32], AccessRights::READ_ADD_WRITE),
+            ces_version: None,
+        }
+    }
+
+    fn deploy_processed_message(id: u32) -> String {
+        let metadata = test_metadata();
+        let execution_result = ExecutionResult::Success {
+            effect: ExecutionEffect {
+                operations: vec![],
+                transforms: vec![
+                    event_transform(&metadata, id, &test_event(id as u64)).unwrap(),
+                    events_length_transform(&metadata, id + 1).unwrap(),
+                ],
+            },
+            transfers: vec![],
+            cost: U512::zero(),
+        };
+
+        let deploy_processed = serde_json::json!({
+            "deploy_hash": "22".repeat(32),
+            "block_hash": "33".repeat(32),
+            "execution_result": execution_result,
+        });
+        // Message is matched by the key, regardless of formatting.
+        format!("data:{{ \"DeployProcessed\": {} }}\n", deploy_processed)
+    }
+
+    #[test]
+    fn test_sse_parser_split_chunks() {
+        let mut parser = SseParser::default();
+
+        let messages = parser.push(b"data:{\"ApiVersion\":\"1.5.6\"}\n\ndata:{\"Block");
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].id, None);
+        assert_eq!(messages[0].data, "{\"ApiVersion\":\"1.5.6\"}");
+
+        let messages = parser.push(b"Added\":{}}\r\nid:7\r\n\r\n: keep-alive\n\n");
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].id, Some(7));
+        assert_eq!(messages[0].data, "{\"BlockAdded\":{}}");
+    }
+
+    #[tokio::test]
+    async fn test_listener_reconnects_with_start_from() {
+        let server = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = server.local_addr().unwrap();
+
+        let server_task = tokio::spawn(async move {
+            let mut requests = vec![];
+            for id in [1u32, 2] {
+                let (mut socket, _) = server.accept().await.unwrap();
+                let mut request = vec![0; 1024];
+                let read = socket.read(&mut request).await.unwrap();
+                requests.push(String::from_utf8_lossy(&request[..read]).to_string());
+
+                // Send single event and close the connection.
+                let body = format!(
+                    "data:{{\"ApiVersion\":\"1.5.6\"}}\n\n{}id:{}\n\n",
+                    deploy_processed_message(id),
+                    id + 9
+                );
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nConnection: close\r\n\r\n{}",
+                    body
+                );
+                socket.write_all(response.as_bytes()).await.unwrap();
+                socket.shutdown().await.unwrap();
+            }
+            requests
+        });
+
+        let listener = EventListener::new(&format!("http://{}/events/main", address))
+            .with_reconnect_delay(Duration::from_millis(10));
+        let metadata = test_metadata();
+        let schemas = test_schemas();
+        let events: Vec<_> = listener
+            .listen(&metadata, &schemas, None)
+            .take(2)
+            .collect()
+            .await;
+
+        let ids: Vec<u32> = events
+            .into_iter()
+            .map(|deploy_events| {
+                let deploy_events = deploy_events.unwrap();
+                assert_eq!(deploy_events.block_hash, Digest::from([0x33; 32]));
+                assert_eq!(deploy_events.events.len(), 1);
+                deploy_events.events[0].context.event_id
+            })
+            .collect();
+        assert_eq!(ids, vec![1, 2]);
+
+        let requests = server_task.await.unwrap();
+        assert!(requests[0].starts_with("GET /events/main HTTP/1.1"));
+        assert!(requests[1].starts_with("GET /events/main?start_from=11 HTTP/1.1"));
+    }
+}
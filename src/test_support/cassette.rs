@@ -0,0 +1,463 @@
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::task::JoinHandle;
+
+use crate::error::ToolkitError;
+use crate::rpc::client::CasperClient;
+use crate::utils::write_atomically;
+
+use super::http::{rpc_error, spawn_server, HttpRequest, HttpResponse, RpcRequest};
+
+/// JSON-RPC interactions recorded from a real node.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Cassette {
+    pub interactions: Vec<Interaction>,
+}
+
+/// Single JSON-RPC request together with the node response.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Interaction {
+    pub method: String,
+    pub params: Value,
+    /// Response object without the request ID.
+    pub response: Value,
+}
+
+impl Cassette {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ToolkitError> {
+        let content = std::fs::read(path)?;
+
+        serde_json::from_slice(&content).map_err(|_e| ToolkitError::DeserializationError {
+            context: "cassette",
+        })
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), ToolkitError> {
+        let content =
+            serde_json::to_vec_pretty(self).map_err(|_e| ToolkitError::SerializationError {
+                context: "cassette",
+            })?;
+
+        write_atomically(path.as_ref(), content)
+    }
+}
+
+/// Local proxy in front of a real node, recording every JSON-RPC interaction into cassette file.
+///
+/// Interactions are kept in memory, and the cassette file is written once - by `finish`, or
+/// when the proxy is dropped. Server is stopped at the same time.
+///
+/// Request headers, e.g. authorization, are forwarded to the node. Requests the node failed to
+/// answer are not recorded, and are answered with `502 Bad Gateway`, so the client can retry
+/// them or fail over to another endpoint.
+pub struct RecordingProxy {
+    address: SocketAddr,
+    cassette: Arc<Mutex<Cassette>>,
+    // Taken once the cassette is saved.
+    cassette_path: Option<PathBuf>,
+    server_task: JoinHandle<()>,
+}
+
+impl RecordingProxy {
+    pub async fn start(
+        upstream_endpoint: &str,
+        cassette_path: impl Into<PathBuf>,
+    ) -> Result<Self, ToolkitError> {
+        // Use the same URL as `casper_client` would do.
+        let upstream_endpoint = upstream_endpoint.trim_end_matches('/');
+        let upstream_url = match upstream_endpoint.ends_with("rpc") {
+            true => upstream_endpoint.to_string(),
+            false => format!("{}/rpc", upstream_endpoint),
+        };
+        let cassette = Arc::new(Mutex::new(Cassette::default()));
+        let http_client = reqwest::Client::new();
+
+        let proxy_cassette = cassette.clone();
+        let (address, server_task) = spawn_server(move |request| {
+            let cassette = proxy_cassette.clone();
+            let http_client = http_client.clone();
+            let upstream_url = upstream_url.clone();
+            async move {
+                let rpc_requests = match parse_request(&request.body) {
+                    Ok((rpc_requests, _batch)) => rpc_requests,
+                    Err(e) => return HttpResponse::ok(rpc_error(Value::Null, &e.to_string())),
+                };
+                match forward_request(&http_client, &upstream_url, request).await {
+                    Ok(response) => {
+                        let interactions = record_interactions(rpc_requests, &response);
+                        cassette.lock().unwrap().interactions.extend(interactions);
+                        HttpResponse::ok(response)
+                    }
+                    Err(e) => HttpResponse::bad_gateway(&e.to_string()),
+                }
+            }
+        })
+        .await?;
+
+        Ok(Self {
+            address,
+            cassette,
+            cassette_path: Some(cassette_path.into()),
+            server_task,
+        })
+    }
+
+    pub fn rpc_endpoint(&self) -> String {
+        format!("http://{}/rpc", self.address)
+    }
+
+    /// Creates client sending requests through the proxy.
+    pub fn client(&self) -> CasperClient {
+        CasperClient::new(&self.rpc_endpoint())
+    }
+
+    /// Returns interactions recorded so far.
+    pub fn cassette(&self) -> Cassette {
+        self.cassette.lock().unwrap().clone()
+    }
+
+    /// Stops the proxy and saves the cassette.
+    pub fn finish(mut self) -> Result<(), ToolkitError> {
+        self.server_task.abort();
+        self.save()
+    }
+
+    fn save(&mut self) -> Result<(), ToolkitError> {
+        match self.cassette_path.take() {
+            Some(cassette_path) => self.cassette().save(cassette_path),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for RecordingProxy {
+    fn drop(&mut self) {
+        self.server_task.abort();
+        // Errors can not be reported here, `finish` returns them.
+        let _ = self.save();
+    }
+}
+
+// Headers describing the connection to the proxy, not the request itself.
+const NOT_FORWARDED_HEADERS: [&str; 4] =
+    ["host", "content-length", "connection", "transfer-encoding"];
+
+async fn forward_request(
+    http_client: &reqwest::Client,
+    upstream_url: &str,
+    request: HttpRequest,
+) -> Result<String, ToolkitError> {
+    let mut upstream_request = http_client.post(upstream_url).body(request.body);
+    for (name, value) in &request.headers {
+        if !NOT_FORWARDED_HEADERS.contains(&name.as_str()) {
+            upstream_request = upstream_request.header(name, value);
+        }
+    }
+    let response = upstream_request
+        .send()
+        .await?
+        .error_for_status()?
+        .text()
+        .await?;
+
+    // Response is recorded only if it is a valid JSON.
+    serde_json::from_str::<Value>(&response).map_err(|_e| ToolkitError::DeserializationError {
+        context: "json-rpc response",
+    })?;
+
+    Ok(response)
+}
+
+// Batch request is recorded as one interaction per call.
+fn record_interactions(rpc_requests: Vec<RpcRequest>, response: &str) -> Vec<Interaction> {
+    let rpc_response: Value = serde_json::from_str(response).unwrap_or_default();
+    rpc_requests
+        .into_iter()
+        .map(|rpc_request| {
+            // Batch responses can come in any order.
+            let mut rpc_response = match &rpc_response {
+                Value::Array(rpc_responses) => rpc_responses
+                    .iter()
+                    .find(|rpc_response| rpc_response["id"] == rpc_request.id)
+                    .cloned()
+                    .unwrap_or_default(),
+                rpc_response => rpc_response.clone(),
+            };
+            if let Some(rpc_response) = rpc_response.as_object_mut() {
+                rpc_response.remove("id");
+            }
+            Interaction {
+                method: rpc_request.method,
+                params: rpc_request.params,
+                response: rpc_response,
+            }
+        })
+        .collect()
+}
+
+// Returns calls of the request, and whether it is a batch.
+fn parse_request(request: &[u8]) -> Result<(Vec<RpcRequest>, bool), ToolkitError> {
+    let deserialization_error = |_e| ToolkitError::DeserializationError {
+        context: "json-rpc request",
+    };
+    match serde_json::from_slice(request).map_err(deserialization_error)? {
+        Value::Array(rpc_requests) => {
+            let rpc_requests = rpc_requests
+                .into_iter()
+                .map(serde_json::from_value)
+                .collect::<Result<_, _>>()
+                .map_err(deserialization_error)?;
+            Ok((rpc_requests, true))
+        }
+        rpc_request => {
+            let rpc_request = serde_json::from_value(rpc_request).map_err(deserialization_error)?;
+            Ok((vec![rpc_request], false))
+        }
+    }
+}
+
+/// JSON-RPC server answering requests with the responses recorded in cassette.
+///
+/// Requests are matched by method and params. Repeated requests get subsequent recorded
+/// responses, and the last one once all of them were used - e.g. polling for the latest state
+/// root hash replays the exact recorded sequence. Server is stopped when dropped.
+pub struct ReplayNode {
+    address: SocketAddr,
+    request_count: Arc<AtomicU64>,
+    server_task: JoinHandle<()>,
+}
+
+impl ReplayNode {
+    pub async fn start(cassette: Cassette) -> Result<Self, ToolkitError> {
+        let interactions: Vec<(Interaction, bool)> = cassette
+            .interactions
+            .into_iter()
+            .map(|interaction| (interaction, false))
+            .collect();
+        let interactions = Arc::new(Mutex::new(interactions));
+        let request_count = Arc::new(AtomicU64::new(0));
+
+        let server_count = request_count.clone();
+        let (address, server_task) = spawn_server(move |request| {
+            let interactions = interactions.clone();
+            server_count.fetch_add(1, Ordering::Relaxed);
+            async move {
+                let mut interactions = interactions.lock().unwrap();
+                HttpResponse::ok(replay_request(&mut interactions, &request.body))
+            }
+        })
+        .await?;
+
+        Ok(Self {
+            address,
+            request_count,
+            server_task,
+        })
+    }
+
+    pub async fn from_file(cassette_path: impl AsRef<Path>) -> Result<Self, ToolkitError> {
+        Self::start(Cassette::load(cassette_path)?).await
+    }
+
+    pub fn rpc_endpoint(&self) -> String {
+        format!("http://{}/rpc", self.address)
+    }
+
+    /// Creates client reading from the cassette.
+    pub fn client(&self) -> CasperClient {
+        CasperClient::new(&self.rpc_endpoint())
+    }
+
+    /// Returns number of received HTTP requests - batch request is counted once.
+    pub fn request_count(&self) -> u64 {
+        self.request_count.load(Ordering::Relaxed)
+    }
+}
+
+impl Drop for ReplayNode {
+    fn drop(&mut self) {
+        self.server_task.abort();
+    }
+}
+
+// Finds recorded responses for the request calls, marking them as used.
+fn replay_request(interactions: &mut [(Interaction, bool)], request: &[u8]) -> String {
+    match parse_request(request) {
+        Ok((rpc_requests, true)) => {
+            let responses: Vec<String> = rpc_requests
+                .into_iter()
+                .map(|rpc_request| replay_call(interactions, rpc_request))
+                .collect();
+            format!("[{}]", responses.join(","))
+        }
+        Ok((mut rpc_requests, false)) => replay_call(interactions, rpc_requests.remove(0)),
+        Err(e) => rpc_error(Value::Null, &e.to_string()),
+    }
+}
+
+fn replay_call(interactions: &mut [(Interaction, bool)], request: RpcRequest) -> String {
+    let mut matching = interactions.iter_mut().filter(|(interaction, _used)| {
+        interaction.method == request.method && interaction.params == request.params
+    });
+    let mut last_matching = None;
+    let replayed = loop {
+        match matching.next() {
+            Some((interaction, used)) if !*used => {
+                *used = true;
+                break Some(interaction);
+            }
+            Some((interaction, _used)) => last_matching = Some(interaction),
+            None => break last_matching,
+        }
+    };
+
+    match replayed {
+        Some(interaction) => {
+            let mut response = interaction.response.clone();
+            response["id"] = request.id;
+            response.to_string()
+        }
+        None => rpc_error(
+            request.id,
+            &format!("no recorded response for '{}'", request.method),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use casper_hashing::Digest;
+
+    use super::*;
+    use crate::fetcher::Fetcher;
+    use crate::metadata::CesMetadataRef;
+    use crate::source::GlobalStateSource;
+    use crate::source::MemorySource;
+    use crate::test_support::{test_event, test_schemas, MockNode};
+
+    async fn fetch_values(client: CasperClient, contract_hash: &str) -> Vec<u64> {
+        let ces_metadata = CesMetadataRef::fetch_metadata(&client, contract_hash)
+            .await
+            .unwrap();
+        let fetcher = Fetcher::new(client, ces_metadata);
+        let schemas = fetcher.fetch_schema().await.unwrap();
+        let events_count = fetcher.fetch_events_count().await.unwrap();
+
+        fetcher
+            .fetch_events(0..events_count, &schemas)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|(_id, event)| event.unwrap().event.fields[0].1.clone().into_t().unwrap())
+            .collect()
+    }
+
+    // Records and replays reading events, returning values and requests count of both runs.
+    async fn record_and_replay(max_batch_size: usize) -> ((Vec<u64>, u64), (Vec<u64>, u64)) {
+        let contract_hash = [1; 32];
+        let mut source = MemorySource::new();
+        let ces_metadata = source
+            .add_ces_contract(contract_hash, &test_schemas())
+            .unwrap();
+        for value in [10u64, 20, 30] {
+            source
+                .push_event(&ces_metadata, &test_event(value))
+                .unwrap();
+        }
+        let node = MockNode::start(source).await.unwrap();
+
+        // Record.
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("cassette.json");
+        let proxy = RecordingProxy::start(&node.rpc_endpoint(), &path)
+            .await
+            .unwrap();
+        let client = proxy.client().with_batch_requests(max_batch_size);
+        let values = fetch_values(client, &hex::encode(contract_hash)).await;
+        let recorded = (values, node.request_count());
+        proxy.finish().unwrap();
+        drop(node);
+
+        // Replay without the node.
+        let replay_node = ReplayNode::from_file(&path).await.unwrap();
+        let client = replay_node.client().with_batch_requests(max_batch_size);
+        let values = fetch_values(client, &hex::encode(contract_hash)).await;
+        let replayed = (values, replay_node.request_count());
+
+        (recorded, replayed)
+    }
+
+    #[tokio::test]
+    async fn test_record_and_replay() {
+        let (recorded, replayed) = record_and_replay(1).await;
+
+        assert_eq!(recorded.0, vec![10, 20, 30]);
+        assert_eq!(replayed, recorded);
+    }
+
+    #[tokio::test]
+    async fn test_record_and_replay_batch() {
+        let (single_recorded, _) = record_and_replay(1).await;
+        let (recorded, replayed) = record_and_replay(2).await;
+
+        assert_eq!(recorded.0, vec![10, 20, 30]);
+        assert_eq!(replayed, recorded);
+        // Three events are read with two batches instead of three requests, so none of the
+        // batches was rejected.
+        assert_eq!(recorded.1, single_recorded.1 - 1);
+    }
+
+    #[tokio::test]
+    async fn test_proxy_forwards_headers() {
+        let mut source = MemorySource::new();
+        source.set_state_root_hash(Digest::from([1; 32]));
+        let node = MockNode::start(source).await.unwrap();
+        let directory = tempfile::tempdir().unwrap();
+        let proxy = RecordingProxy::start(&node.rpc_endpoint(), directory.path().join("cassette"))
+            .await
+            .unwrap();
+
+        let client = proxy
+            .client()
+            .with_header("X-Api-Key", "key")
+            .with_bearer_token("token");
+        client.get_state_root_hash(None).await.unwrap();
+        assert_eq!(
+            node.last_request_header("x-api-key").as_deref(),
+            Some("key")
+        );
+        assert_eq!(
+            node.last_request_header("authorization").as_deref(),
+            Some("Bearer token")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_proxy_reports_upstream_failure() {
+        // Nothing listens on the port once the listener is dropped.
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let upstream_endpoint = format!("http://{}", listener.local_addr().unwrap());
+        drop(listener);
+        let directory = tempfile::tempdir().unwrap();
+        let proxy = RecordingProxy::start(&upstream_endpoint, directory.path().join("cassette"))
+            .await
+            .unwrap();
+
+        let error = proxy.client().get_state_root_hash(None).await.unwrap_err();
+        assert!(matches!(
+            &error,
+            ToolkitError::RpcError { error } if matches!(
+                error.as_ref(),
+                casper_client::Error::ResponseIsHttpError { error, .. }
+                    if error.status() == Some(reqwest::StatusCode::BAD_GATEWAY)
+            )
+        ));
+        assert!(error.is_retryable());
+        assert!(proxy.cassette().interactions.is_empty());
+    }
+}
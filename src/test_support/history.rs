@@ -0,0 +1,149 @@
+use async_trait::async_trait;
+use casper_client::rpcs::common::BlockIdentifier;
+use casper_event_standard::casper_types::account::AccountHash;
+use casper_event_standard::casper_types::contracts::NamedKeys;
+use casper_event_standard::casper_types::{CLValue, HashAddr, URef};
+use casper_hashing::Digest;
+
+use crate::error::ToolkitError;
+use crate::rpc::types::{BlockInfo, ContractPackageInfo, DeployExecution};
+use crate::source::{GlobalStateSource, MemorySource};
+
+/// Global state at every block height, for testing reads pinned to older blocks.
+///
+/// State root hash of the block is filled with its height, and the last state is the latest
+/// one. Deploys and blocks are not served.
+pub struct HistorySource {
+    states: Vec<MemorySource>,
+    lowest_available_height: u64,
+}
+
+impl HistorySource {
+    /// Creates source from the states ordered by block height, starting from 0.
+    pub fn new(states: Vec<MemorySource>) -> Self {
+        Self {
+            states,
+            lowest_available_height: 0,
+        }
+    }
+
+    /// Makes older blocks unavailable, as on the node synced from the given height.
+    pub fn with_lowest_available_height(mut self, lowest_available_height: u64) -> Self {
+        self.lowest_available_height = lowest_available_height;
+        self
+    }
+
+    /// Returns state root hash of the block at given height.
+    pub fn state_root_hash(height: u8) -> Digest {
+        Digest::from([height; 32])
+    }
+
+    fn state(&self, state_root_hash: Digest) -> Result<&MemorySource, ToolkitError> {
+        self.states
+            .get(state_root_hash.value()[0] as usize)
+            .ok_or_else(|| ToolkitError::MissingStoredValue {
+                context: format!("state root hash {}", state_root_hash),
+            })
+    }
+}
+
+#[async_trait]
+impl GlobalStateSource for HistorySource {
+    async fn get_state_root_hash(
+        &self,
+        block_identifier: Option<BlockIdentifier>,
+    ) -> Result<Digest, ToolkitError> {
+        let height = match block_identifier {
+            Some(BlockIdentifier::Height(height)) => height,
+            _ => self.states.len() as u64 - 1,
+        };
+        if height < self.lowest_available_height {
+            return Err(ToolkitError::MissingBlock);
+        }
+        Ok(Self::state_root_hash(height as u8))
+    }
+
+    async fn get_contract_named_keys(
+        &self,
+        state_root_hash: Digest,
+        contract_hash: HashAddr,
+    ) -> Result<NamedKeys, ToolkitError> {
+        self.state(state_root_hash)?
+            .get_contract_named_keys(state_root_hash, contract_hash)
+            .await
+    }
+
+    async fn get_account_named_keys(
+        &self,
+        state_root_hash: Digest,
+        account_hash: AccountHash,
+    ) -> Result<NamedKeys, ToolkitError> {
+        self.state(state_root_hash)?
+            .get_account_named_keys(state_root_hash, account_hash)
+            .await
+    }
+
+    async fn get_contract_package(
+        &self,
+        state_root_hash: Digest,
+        contract_package_hash: HashAddr,
+    ) -> Result<ContractPackageInfo, ToolkitError> {
+        self.state(state_root_hash)?
+            .get_contract_package(state_root_hash, contract_package_hash)
+            .await
+    }
+
+    async fn get_stored_clvalue(
+        &self,
+        state_root_hash: Digest,
+        uref: &URef,
+    ) -> Result<CLValue, ToolkitError> {
+        self.state(state_root_hash)?
+            .get_stored_clvalue(state_root_hash, uref)
+            .await
+    }
+
+    async fn get_stored_clvalue_from_dict(
+        &self,
+        state_root_hash: Digest,
+        dictionary_seed_uref: &URef,
+        dictionary_item_key: &str,
+    ) -> Result<CLValue, ToolkitError> {
+        self.state(state_root_hash)?
+            .get_stored_clvalue_from_dict(
+                state_root_hash,
+                dictionary_seed_uref,
+                dictionary_item_key,
+            )
+            .await
+    }
+
+    async fn get_deploy_result(
+        &self,
+        _deploy_hash: Digest,
+    ) -> Result<DeployExecution, ToolkitError> {
+        Err(ToolkitError::MissingDeploy)
+    }
+
+    async fn get_block(
+        &self,
+        _block_identifier: BlockIdentifier,
+    ) -> Result<BlockInfo, ToolkitError> {
+        Err(ToolkitError::MissingBlock)
+    }
+
+    async fn get_latest_block(&self) -> Result<BlockInfo, ToolkitError> {
+        let state_root_hash = self.get_state_root_hash(None).await?;
+        Ok(BlockInfo {
+            hash: Digest::default(),
+            height: self.states.len() as u64 - 1,
+            timestamp: 0,
+            state_root_hash,
+            deploy_hashes: vec![],
+        })
+    }
+
+    async fn get_lowest_available_block_height(&self) -> Result<u64, ToolkitError> {
+        Ok(self.lowest_available_height)
+    }
+}
@@ -0,0 +1,141 @@
+use std::future::Future;
+use std::net::SocketAddr;
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+
+use crate::error::ToolkitError;
+
+// Error code returned for every failed request.
+const RPC_ERROR_CODE: i64 = -32003;
+
+#[derive(Deserialize)]
+pub(super) struct RpcRequest {
+    pub id: Value,
+    pub method: String,
+    #[serde(default)]
+    pub params: Value,
+}
+
+/// HTTP request received by the server.
+pub(super) struct HttpRequest {
+    /// Header names are lowercase.
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+/// HTTP response sent by the server.
+pub(super) struct HttpResponse {
+    pub status: u16,
+    pub body: String,
+}
+
+impl HttpResponse {
+    pub fn ok(body: String) -> Self {
+        Self { status: 200, body }
+    }
+
+    /// Failure of the upstream server, e.g. unreachable one.
+    pub fn bad_gateway(message: &str) -> Self {
+        Self {
+            status: 502,
+            body: message.to_string(),
+        }
+    }
+}
+
+/// Starts HTTP server on a random local port, answering every request with the handler.
+pub(super) async fn spawn_server<H, Fut>(
+    handler: H,
+) -> Result<(SocketAddr, JoinHandle<()>), ToolkitError>
+where
+    H: Fn(HttpRequest) -> Fut + Clone + Send + 'static,
+    Fut: Future<Output = HttpResponse> + Send + 'static,
+{
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let address = listener.local_addr()?;
+
+    let server_task = tokio::spawn(async move {
+        while let Ok((socket, _)) = listener.accept().await {
+            tokio::spawn(serve_connection(socket, handler.clone()));
+        }
+    });
+
+    Ok((address, server_task))
+}
+
+pub(super) fn rpc_error(id: Value, message: &str) -> String {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": {"code": RPC_ERROR_CODE, "message": message},
+    })
+    .to_string()
+}
+
+// Handles HTTP requests sent over a single, possibly kept alive, connection.
+async fn serve_connection<H, Fut>(mut socket: TcpStream, handler: H)
+where
+    H: Fn(HttpRequest) -> Fut,
+    Fut: Future<Output = HttpResponse>,
+{
+    let mut buffer = vec![];
+    let mut chunk = [0; 4096];
+
+    loop {
+        // Wait for complete request.
+        let request = loop {
+            if let Some(request) = take_http_request(&mut buffer) {
+                break request;
+            }
+            match socket.read(&mut chunk).await {
+                Ok(0) | Err(_) => return,
+                Ok(read) => buffer.extend_from_slice(&chunk[..read]),
+            }
+        };
+
+        let response = handler(request).await;
+        let reason = match response.status {
+            200 => "OK",
+            _ => "Bad Gateway",
+        };
+        let http_response = format!(
+            "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+            response.status,
+            reason,
+            response.body.len(),
+            response.body
+        );
+        if socket.write_all(http_response.as_bytes()).await.is_err() {
+            return;
+        }
+    }
+}
+
+// Removes complete HTTP request from the buffer.
+fn take_http_request(buffer: &mut Vec<u8>) -> Option<HttpRequest> {
+    let headers_end = buffer.windows(4).position(|window| window == b"\r\n\r\n")? + 4;
+    // First line is the request line.
+    let headers: Vec<(String, String)> = String::from_utf8_lossy(&buffer[..headers_end])
+        .lines()
+        .skip(1)
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_lowercase(), value.trim().to_string()))
+        .collect();
+    let content_length = headers
+        .iter()
+        .find(|(name, _)| name == "content-length")
+        .and_then(|(_, value)| value.parse::<usize>().ok())
+        .unwrap_or(0);
+
+    if buffer.len() < headers_end + content_length {
+        return None;
+    }
+    let body = buffer[headers_end..headers_end + content_length].to_vec();
+    buffer.drain(..headers_end + content_length);
+
+    Some(HttpRequest { headers, body })
+}
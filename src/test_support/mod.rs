@@ -0,0 +1,643 @@
+//! Local JSON-RPC server imitating the Casper node, and other helpers for integration tests.
+
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use casper_client::rpcs::common::BlockIdentifier;
+use casper_client::rpcs::GlobalStateIdentifier;
+use casper_client_types::bytesrepr::ToBytes;
+use casper_event_standard::casper_types;
+use casper_event_standard::{Schema, Schemas};
+use casper_hashing::Digest;
+use casper_types::account::AccountHash;
+use casper_types::bytesrepr::{Bytes, ToBytes as _};
+use casper_types::{
+    AccessRights, CLType, CLValue, ContractHash, ContractPackageHash, ContractWasmHash, HashAddr,
+    Key, ProtocolVersion, PublicKey, Transform, TransformEntry, URef,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::sync::{RwLock, RwLockWriteGuard};
+use tokio::task::JoinHandle;
+
+use crate::error::ToolkitError;
+use crate::event::Event;
+use crate::metadata::CesMetadataRef;
+use crate::rpc::compat;
+use crate::rpc::types::{BlockInfo, ContractPackageInfo};
+use crate::source::{GlobalStateSource, MemorySource};
+
+use self::http::{rpc_error, spawn_server, HttpResponse, RpcRequest};
+use self::trie::StateTrie;
+
+mod cassette;
+mod history;
+mod http;
+pub(crate) mod trie;
+
+pub use cassette::{Cassette, Interaction, RecordingProxy, ReplayNode};
+pub use history::HistorySource;
+
+const API_VERSION: &str = "1.5.6";
+
+/// JSON-RPC server answering the requests made by `CasperClient` with the data of
+/// `MemorySource`.
+///
+/// Blocks served by the node must have valid hashes, so they should be built with `mock_block`.
+/// Server is stopped when dropped.
+pub struct MockNode {
+    address: SocketAddr,
+    source: Arc<RwLock<MemorySource>>,
+    request_count: Arc<AtomicU64>,
+    batches_rejected: Arc<AtomicBool>,
+    last_request_headers: Arc<Mutex<Vec<(String, String)>>>,
+    response_delay: Arc<Mutex<Duration>>,
+    server_task: JoinHandle<()>,
+}
+
+impl MockNode {
+    /// Starts the server on a random local port.
+    pub async fn start(source: MemorySource) -> Result<Self, ToolkitError> {
+        let source = Arc::new(RwLock::new(source));
+        let request_count = Arc::new(AtomicU64::new(0));
+        let batches_rejected = Arc::new(AtomicBool::new(false));
+        let last_request_headers = Arc::new(Mutex::new(vec![]));
+        let response_delay = Arc::new(Mutex::new(Duration::ZERO));
+
+        let server_source = source.clone();
+        let server_count = request_count.clone();
+        let server_batches_rejected = batches_rejected.clone();
+        let server_headers = last_request_headers.clone();
+        let server_delay = response_delay.clone();
+        let (address, server_task) = spawn_server(move |request| {
+            let source = server_source.clone();
+            server_count.fetch_add(1, Ordering::Relaxed);
+            let batches_rejected = server_batches_rejected.load(Ordering::Relaxed);
+            *server_headers.lock().unwrap() = request.headers;
+            let response_delay = *server_delay.lock().unwrap();
+            async move {
+                tokio::time::sleep(response_delay).await;
+                let source = source.read().await;
+                HttpResponse::ok(
+                    handle_http_request(&request.body, &source, batches_rejected).await,
+                )
+            }
+        })
+        .await?;
+
+        Ok(Self {
+            address,
+            source,
+            request_count,
+            batches_rejected,
+            last_request_headers,
+            response_delay,
+            server_task,
+        })
+    }
+
+    pub fn rpc_endpoint(&self) -> String {
+        format!("http://{}/rpc", self.address)
+    }
+
+    /// Gives access to the served data, e.g. for adding new events.
+    pub async fn source_mut(&self) -> RwLockWriteGuard<'_, MemorySource> {
+        self.source.write().await
+    }
+
+    /// Returns number of received HTTP requests - batch request is counted once.
+    pub fn request_count(&self) -> u64 {
+        self.request_count.load(Ordering::Relaxed)
+    }
+
+    /// Answers batch requests with a single error, as done by nodes not supporting them.
+    pub fn reject_batches(&self, batches_rejected: bool) {
+        self.batches_rejected
+            .store(batches_rejected, Ordering::Relaxed);
+    }
+
+    /// Returns value of the header sent with the last request, e.g. for checking authorization.
+    pub fn last_request_header(&self, name: &str) -> Option<String> {
+        self.last_request_headers
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|(header_name, _)| header_name.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.clone())
+    }
+
+    /// Delays every response, e.g. for testing request timeouts.
+    pub fn set_response_delay(&self, response_delay: Duration) {
+        *self.response_delay.lock().unwrap() = response_delay;
+    }
+}
+
+impl Drop for MockNode {
+    fn drop(&mut self) {
+        self.server_task.abort();
+    }
+}
+
+/// Builds block with the hash computed the same way as by the node.
+pub fn mock_block(
+    height: u64,
+    timestamp: u64,
+    state_root_hash: Digest,
+    deploy_hashes: Vec<Digest>,
+) -> Result<BlockInfo, ToolkitError> {
+    let mut block = BlockInfo {
+        hash: Digest::default(),
+        height,
+        timestamp,
+        state_root_hash,
+        deploy_hashes,
+    };
+    let (header, _body) = block_header_and_body(&block)?;
+    let header: casper_client::types::BlockHeader = from_json(header, "block header")?;
+    block.hash = block_header_hash(&header)?;
+
+    Ok(block)
+}
+
+/// Schemas with the single `Test` event, holding `value` of type `U64` - see `test_event`.
+pub fn test_schemas() -> Schemas {
+    let mut schema = Schema::new();
+    schema.with_elem("value", CLType::U64);
+    let mut schemas = Schemas::new();
+    schemas.0.insert("Test".to_string(), schema);
+    schemas
+}
+
+/// Builds `Test` event with the given value.
+pub fn test_event(value: u64) -> Event {
+    Event {
+        name: "Test".to_string(),
+        fields: vec![(
+            "value".to_string(),
+            CLValue::from_t(value).expect("u64 is serializable"),
+        )],
+    }
+}
+
+/// Builds transform writing the event under the given ID, as done by the contract emitting it.
+pub fn event_transform(
+    ces_metadata: &CesMetadataRef,
+    id: u32,
+    event: &Event,
+) -> Result<TransformEntry, ToolkitError> {
+    let event_value = CLValue::from_t(Bytes::from(event.to_ces_bytes()?))
+        .map_err(|e| ToolkitError::InvalidCLValue(e.to_string()))?;
+    // Dictionary value wraps the stored CLValue.
+    let dictionary_value =
+        event_value
+            .to_bytes()
+            .map_err(|_e| ToolkitError::SerializationError {
+                context: "dictionary value",
+            })?;
+    let key = Key::dictionary(ces_metadata.events_data, id.to_string().as_bytes());
+
+    Ok(TransformEntry {
+        key: key.to_formatted_string(),
+        transform: Transform::WriteCLValue(CLValue::from_components(CLType::Any, dictionary_value)),
+    })
+}
+
+/// Builds transform writing the events count, following the event writes.
+pub fn events_length_transform(
+    ces_metadata: &CesMetadataRef,
+    events_length: u32,
+) -> Result<TransformEntry, ToolkitError> {
+    let events_length =
+        CLValue::from_t(events_length).map_err(|e| ToolkitError::InvalidCLValue(e.to_string()))?;
+
+    Ok(TransformEntry {
+        key: Key::URef(ces_metadata.events_length).to_formatted_string(),
+        transform: Transform::WriteCLValue(events_length),
+    })
+}
+
+/// Returns state root hash of the Merkle proofs served by `MockNode` for the source.
+///
+/// Proofs are served for URef values and dictionary items. They can be verified by the client if
+/// the block read by it has this state root hash.
+pub fn merkle_state_root_hash(source: &MemorySource) -> Result<Digest, ToolkitError> {
+    Ok(StateTrie::new(source.stored_values()?)?.root_hash())
+}
+
+async fn handle_http_request(
+    request: &[u8],
+    source: &MemorySource,
+    batches_rejected: bool,
+) -> String {
+    // Batch request is answered with the array of responses.
+    match serde_json::from_slice::<Vec<Value>>(request) {
+        Ok(_) if batches_rejected => rpc_error(Value::Null, "batch requests not supported"),
+        Ok(requests) => {
+            let mut responses = vec![];
+            for request in requests {
+                responses.push(handle_rpc_request(request.to_string().as_bytes(), source).await);
+            }
+            format!("[{}]", responses.join(","))
+        }
+        Err(_) => handle_rpc_request(request, source).await,
+    }
+}
+
+async fn handle_rpc_request(request: &[u8], source: &MemorySource) -> String {
+    let request: RpcRequest = match serde_json::from_slice(request) {
+        Ok(request) => request,
+        Err(e) => return rpc_error(Value::Null, &e.to_string()),
+    };
+
+    let result = match request.method.as_str() {
+        "chain_get_state_root_hash" => get_state_root_hash(source, request.params).await,
+        "query_global_state" => query_global_state(source, request.params).await,
+        "state_get_dictionary_item" => get_dictionary_item(source, request.params).await,
+        "info_get_deploy" => get_deploy(source, request.params).await,
+        "chain_get_block" => get_block(source, request.params).await,
+        "info_get_status" => get_status(source).await,
+        method => Err(ToolkitError::UnexpectedError {
+            context: format!("unsupported method '{}'", method),
+        }),
+    };
+
+    match result {
+        Ok(mut result) => {
+            result["api_version"] = json!(API_VERSION);
+            json!({"jsonrpc": "2.0", "id": request.id, "result": result}).to_string()
+        }
+        Err(e) => rpc_error(request.id, &e.to_string()),
+    }
+}
+
+#[derive(Deserialize)]
+struct GetStateRootHashParams {
+    block_identifier: Option<BlockIdentifier>,
+}
+
+async fn get_state_root_hash(source: &MemorySource, params: Value) -> Result<Value, ToolkitError> {
+    // Params are omitted when asking for the latest state root hash.
+    let block_identifier = match params {
+        Value::Null => None,
+        params => {
+            let params: GetStateRootHashParams = from_json(params, "state root hash params")?;
+            params.block_identifier
+        }
+    };
+    let state_root_hash = source.get_state_root_hash(block_identifier).await?;
+
+    Ok(json!({ "state_root_hash": state_root_hash }))
+}
+
+#[derive(Deserialize)]
+struct QueryGlobalStateParams {
+    state_identifier: GlobalStateIdentifier,
+    key: String,
+}
+
+async fn query_global_state(source: &MemorySource, params: Value) -> Result<Value, ToolkitError> {
+    let params: QueryGlobalStateParams = from_json(params, "query global state params")?;
+    let state_root_hash = match params.state_identifier {
+        GlobalStateIdentifier::StateRootHash(state_root_hash) => {
+            compat::digest_from_client_types(&state_root_hash)?
+        }
+        _ => source.get_state_root_hash(None).await?,
+    };
+
+    let key = Key::from_formatted_str(&params.key).map_err(|_e| ToolkitError::InvalidKeyType {
+        context: params.key.clone(),
+    })?;
+    let stored_value = match key {
+        // Hash of a contract package, or of a contract.
+        Key::Hash(hash) => match source.get_contract_package(state_root_hash, hash).await {
+            Ok(contract_package) => contract_package_json(&contract_package),
+            Err(_) => contract_json(source, state_root_hash, hash).await?,
+        },
+        Key::Account(account_hash) => account_json(source, state_root_hash, account_hash).await?,
+        Key::URef(uref) => {
+            let clvalue = source.get_stored_clvalue(state_root_hash, &uref).await?;
+            json!({ "CLValue": clvalue })
+        }
+        _ => {
+            return Err(ToolkitError::InvalidKeyType {
+                context: params.key,
+            })
+        }
+    };
+
+    Ok(json!({
+        "block_header": null,
+        "stored_value": stored_value,
+        "merkle_proof": merkle_proof(source, &key)?,
+    }))
+}
+
+// Proof of the value under the key, empty if it is not proven.
+fn merkle_proof(source: &MemorySource, key: &Key) -> Result<String, ToolkitError> {
+    let merkle_proof = StateTrie::new(source.stored_values()?)?.merkle_proof(key)?;
+    Ok(merkle_proof.unwrap_or_default())
+}
+
+async fn contract_json(
+    source: &MemorySource,
+    state_root_hash: Digest,
+    contract_hash: HashAddr,
+) -> Result<Value, ToolkitError> {
+    let named_keys: Vec<Value> = source
+        .get_contract_named_keys(state_root_hash, contract_hash)
+        .await?
+        .into_iter()
+        .map(|(name, key)| json!({"name": name, "key": key.to_formatted_string()}))
+        .collect();
+
+    Ok(json!({
+        "Contract": {
+            "contract_package_hash": ContractPackageHash::default(),
+            "contract_wasm_hash": ContractWasmHash::default(),
+            "named_keys": named_keys,
+            "entry_points": [],
+            "protocol_version": ProtocolVersion::V1_0_0,
+        }
+    }))
+}
+
+async fn account_json(
+    source: &MemorySource,
+    state_root_hash: Digest,
+    account_hash: AccountHash,
+) -> Result<Value, ToolkitError> {
+    let named_keys: Vec<Value> = source
+        .get_account_named_keys(state_root_hash, account_hash)
+        .await?
+        .into_iter()
+        .map(|(name, key)| json!({"name": name, "key": key.to_formatted_string()}))
+        .collect();
+
+    Ok(json!({
+        "Account": {
+            "account_hash": account_hash,
+            "named_keys": named_keys,
+            "main_purse": URef::new([0; 32], AccessRights::READ_ADD_WRITE),
+            "associated_keys": [{ "account_hash": account_hash, "weight": 1 }],
+            "action_thresholds": { "deployment": 1, "key_management": 1 },
+        }
+    }))
+}
+
+fn contract_package_json(contract_package: &ContractPackageInfo) -> Value {
+    let versions: Vec<Value> = contract_package
+        .versions
+        .iter()
+        .map(|version| {
+            json!({
+                "protocol_version_major": version.protocol_version_major,
+                "contract_version": version.contract_version,
+                "contract_hash": ContractHash::new(version.contract_hash),
+            })
+        })
+        .collect();
+    let disabled_versions: Vec<Value> = contract_package
+        .versions
+        .iter()
+        .filter(|version| !version.enabled)
+        .map(|version| {
+            json!({
+                "protocol_version_major": version.protocol_version_major,
+                "contract_version": version.contract_version,
+            })
+        })
+        .collect();
+
+    json!({
+        "ContractPackage": {
+            "access_key": URef::new([0; 32], AccessRights::READ_ADD_WRITE),
+            "versions": versions,
+            "disabled_versions": disabled_versions,
+            "groups": [],
+            "lock_status": "Unlocked",
+        }
+    })
+}
+
+#[derive(Deserialize)]
+struct GetDictionaryItemParams {
+    state_root_hash: Digest,
+    dictionary_identifier: DictionaryIdentifier,
+}
+
+#[derive(Deserialize)]
+enum DictionaryIdentifier {
+    URef {
+        seed_uref: URef,
+        dictionary_item_key: String,
+    },
+}
+
+async fn get_dictionary_item(source: &MemorySource, params: Value) -> Result<Value, ToolkitError> {
+    let params: GetDictionaryItemParams = from_json(params, "dictionary item params")?;
+    let DictionaryIdentifier::URef {
+        seed_uref,
+        dictionary_item_key,
+    } = params.dictionary_identifier;
+
+    let clvalue = source
+        .get_stored_clvalue_from_dict(params.state_root_hash, &seed_uref, &dictionary_item_key)
+        .await?;
+    let dictionary_key = Key::dictionary(seed_uref, dictionary_item_key.as_bytes());
+
+    Ok(json!({
+        "dictionary_key": dictionary_key.to_formatted_string(),
+        "stored_value": { "CLValue": clvalue },
+        "merkle_proof": merkle_proof(source, &dictionary_key)?,
+    }))
+}
+
+#[derive(Deserialize)]
+struct GetDeployParams {
+    deploy_hash: Digest,
+}
+
+async fn get_deploy(source: &MemorySource, params: Value) -> Result<Value, ToolkitError> {
+    let params: GetDeployParams = from_json(params, "deploy params")?;
+    let deploy_execution = source.get_deploy_result(params.deploy_hash).await?;
+
+    // Only execution results are read by the client, so the deploy itself is a placeholder.
+    let deploy = json!({
+        "hash": params.deploy_hash,
+        "header": {
+            "account": PublicKey::System,
+            "timestamp": compat::timestamp_millis_to_client_types(0)?,
+            "ttl": "30m",
+            "gas_price": 1,
+            "body_hash": Digest::default(),
+            "dependencies": [],
+            "chain_name": "casper-mock",
+        },
+        "payment": { "ModuleBytes": { "module_bytes": "", "args": [] } },
+        "session": { "ModuleBytes": { "module_bytes": "", "args": [] } },
+        "approvals": [],
+    });
+
+    Ok(json!({
+        "deploy": deploy,
+        "execution_results": [{
+            "block_hash": deploy_execution.block_hash,
+            "result": deploy_execution.execution_result,
+        }],
+    }))
+}
+
+#[derive(Deserialize)]
+struct GetBlockParams {
+    block_identifier: BlockIdentifier,
+}
+
+async fn get_block(source: &MemorySource, params: Value) -> Result<Value, ToolkitError> {
+    // Params are omitted when asking for the latest block.
+    let block = match params {
+        Value::Null => source.get_latest_block().await?,
+        params => {
+            let params: GetBlockParams = from_json(params, "block params")?;
+            source.get_block(params.block_identifier).await?
+        }
+    };
+    let (header, body) = block_header_and_body(&block)?;
+
+    Ok(json!({
+        "block": {
+            "hash": block.hash,
+            "header": header,
+            "body": body,
+            "proofs": [],
+        }
+    }))
+}
+
+async fn get_status(source: &MemorySource) -> Result<Value, ToolkitError> {
+    let low = source.get_lowest_available_block_height().await?;
+    let high = source.latest_block().map_or(low, |block| block.height);
+
+    Ok(json!({ "available_block_range": { "low": low, "high": high } }))
+}
+
+// Builds JSON of the block header and body, with matching body hash.
+fn block_header_and_body(block: &BlockInfo) -> Result<(Value, Value), ToolkitError> {
+    let body = json!({
+        "proposer": PublicKey::System,
+        "deploy_hashes": block.deploy_hashes,
+        "transfer_hashes": [],
+    });
+    let client_body: casper_client::types::BlockBody = from_json(body.clone(), "block body")?;
+    let body_hash = client_body
+        .to_bytes()
+        .map_err(|_e| ToolkitError::SerializationError {
+            context: "block body",
+        })?;
+    let body_hash = casper_client_hashing::Digest::hash(body_hash);
+
+    let header = json!({
+        "parent_hash": Digest::default(),
+        "state_root_hash": block.state_root_hash,
+        "body_hash": body_hash,
+        "random_bit": false,
+        "accumulated_seed": Digest::default(),
+        "era_end": null,
+        "timestamp": compat::timestamp_millis_to_client_types(block.timestamp)?,
+        "era_id": 0,
+        "height": block.height,
+        "protocol_version": ProtocolVersion::V1_0_0,
+    });
+
+    Ok((header, body))
+}
+
+fn block_header_hash(header: &casper_client::types::BlockHeader) -> Result<Digest, ToolkitError> {
+    let header_bytes = header
+        .to_bytes()
+        .map_err(|_e| ToolkitError::SerializationError {
+            context: "block header",
+        })?;
+
+    compat::digest_from_client_types(&casper_client_hashing::Digest::hash(header_bytes))
+}
+
+fn from_json<T: serde::de::DeserializeOwned>(
+    value: Value,
+    context: &'static str,
+) -> Result<T, ToolkitError> {
+    serde_json::from_value(value).map_err(|_e| ToolkitError::DeserializationError { context })
+}
+
+#[cfg(test)]
+mod tests {
+    use casper_types::{ExecutionEffect, ExecutionResult, U512};
+
+    use super::*;
+    use crate::fetcher::Fetcher;
+    use crate::rpc::client::CasperClient;
+    use crate::rpc::types::DeployExecution;
+
+    #[tokio::test]
+    async fn test_client_reads_mock_node() {
+        let schemas = test_schemas();
+        let contract_hash = [1; 32];
+        let mut source = MemorySource::new();
+        source.set_state_root_hash(Digest::from([2; 32]));
+        let ces_metadata = source.add_ces_contract(contract_hash, &schemas).unwrap();
+        source.push_event(&ces_metadata, &test_event(10)).unwrap();
+
+        // Deploy emitting the second event.
+        let deploy_hash = Digest::from([3; 32]);
+        let execution_result = ExecutionResult::Success {
+            effect: ExecutionEffect {
+                operations: vec![],
+                transforms: vec![
+                    event_transform(&ces_metadata, 1, &test_event(20)).unwrap(),
+                    events_length_transform(&ces_metadata, 2).unwrap(),
+                ],
+            },
+            transfers: vec![],
+            cost: U512::zero(),
+        };
+        let block = mock_block(
+            7,
+            1_700_000_000_000,
+            Digest::from([2; 32]),
+            vec![deploy_hash],
+        )
+        .unwrap();
+        source.put_deploy(
+            deploy_hash,
+            DeployExecution {
+                block_hash: block.hash,
+                execution_result,
+            },
+        );
+        source.put_block(block.clone());
+
+        let node = MockNode::start(source).await.unwrap();
+        let client = CasperClient::new(&node.rpc_endpoint());
+        let ces_metadata = CesMetadataRef::fetch_metadata(&client, &hex::encode(contract_hash))
+            .await
+            .unwrap();
+        let fetcher = Fetcher::new(client, ces_metadata);
+
+        let schemas = fetcher.fetch_schema().await.unwrap();
+        assert_eq!(fetcher.fetch_events_count().await.unwrap(), 1);
+        let event = fetcher.fetch_event(0, &schemas).await.unwrap();
+        assert_eq!(event.event.fields[0].1.clone().into_t::<u64>().unwrap(), 10);
+
+        let deploys = fetcher
+            .fetch_events_from_block(BlockIdentifier::Height(7), &schemas)
+            .await
+            .unwrap();
+        assert_eq!(deploys.len(), 1);
+        assert_eq!(deploys[0].deploy_hash, deploy_hash);
+        let context = &deploys[0].events[0].context;
+        assert_eq!(context.event_id, 1);
+        assert_eq!(context.block_hash, Some(block.hash));
+        assert_eq!(context.block_timestamp, Some(1_700_000_000_000));
+    }
+}
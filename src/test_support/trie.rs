@@ -0,0 +1,158 @@
+use casper_event_standard::casper_types;
+use casper_hashing::Digest;
+use casper_types::bytesrepr::{Bytes, ToBytes};
+use casper_types::{Key, StoredValue};
+
+use crate::error::ToolkitError;
+use crate::rpc::proof::{
+    node_bytes, write_pointer, Pointer, PROOF_STEP_EXTENSION_TAG, PROOF_STEP_NODE_TAG,
+    TRIE_EXTENSION_TAG, TRIE_LEAF_TAG,
+};
+
+// Number of children of the trie node.
+const RADIX: usize = 256;
+
+/// Global state trie, serialized and hashed the same way as by the node, for serving Merkle
+/// proofs.
+///
+/// Leaves are grouped by 256 under nodes, each placed under an extension in the root node.
+/// Free slots of every node point to other leaves, as in the busy top of the real trie - so every
+/// node takes more than 8 KiB. Paths do not follow the key bytes, as proofs are verified by
+/// hashing only.
+pub(crate) struct StateTrie {
+    leaves: Vec<(Key, StoredValue)>,
+    // Pointers of the nodes under the root, by group of leaves.
+    groups: Vec<Vec<(u8, Pointer)>>,
+    // Pointers of the root node, to the extensions over the groups.
+    root: Vec<(u8, Pointer)>,
+}
+
+impl StateTrie {
+    pub(crate) fn new(leaves: Vec<(Key, StoredValue)>) -> Result<Self, ToolkitError> {
+        if leaves.len() > RADIX * RADIX {
+            return Err(ToolkitError::UnexpectedError {
+                context: "too many leaves for the state trie".to_string(),
+            });
+        }
+
+        let mut leaf_pointers = vec![];
+        for (key, value) in &leaves {
+            let leaf = leaf_bytes(key, value)?;
+            leaf_pointers.push(Pointer::Leaf(Digest::hash_into_chunks_if_necessary(&leaf)));
+        }
+        let groups_count = leaves.len().div_ceil(RADIX);
+        let groups: Vec<Vec<(u8, Pointer)>> = (0..groups_count)
+            .map(|group| {
+                full_node(|index| {
+                    leaf_pointers
+                        .get(group * RADIX + index)
+                        .copied()
+                        .unwrap_or_else(|| filler_pointer(group, index))
+                })
+            })
+            .collect();
+        let mut extension_pointers = vec![];
+        for (group, pointers) in groups.iter().enumerate() {
+            extension_pointers.push(Pointer::Node(extension_hash(group, pointers)?));
+        }
+        let root = full_node(|group| {
+            extension_pointers
+                .get(group)
+                .copied()
+                .unwrap_or_else(|| filler_pointer(RADIX, group))
+        });
+
+        Ok(Self {
+            leaves,
+            groups,
+            root,
+        })
+    }
+
+    pub(crate) fn root_hash(&self) -> Digest {
+        Digest::hash_into_chunks_if_necessary(&node_bytes(&self.root))
+    }
+
+    /// Returns hex encoded proof of the key, as returned by the node - `None` if not found.
+    pub(crate) fn merkle_proof(&self, key: &Key) -> Result<Option<String>, ToolkitError> {
+        let key = key.normalize();
+        let position = match self
+            .leaves
+            .iter()
+            .position(|(leaf_key, _)| *leaf_key == key)
+        {
+            Some(position) => position,
+            None => return Ok(None),
+        };
+        let (group, index) = (position / RADIX, position % RADIX);
+
+        let (key, value) = &self.leaves[position];
+        let mut proof = serialize(&1u32)?;
+        proof.extend(serialize(key)?);
+        proof.extend(serialize(value)?);
+        proof.extend(serialize(&3u32)?);
+        write_node_step(&mut proof, &self.groups[group], index)?;
+        proof.push(PROOF_STEP_EXTENSION_TAG);
+        proof.extend(serialize(&extension_affix(group))?);
+        write_node_step(&mut proof, &self.root, group)?;
+
+        Ok(Some(hex::encode(proof)))
+    }
+}
+
+fn leaf_bytes(key: &Key, value: &StoredValue) -> Result<Vec<u8>, ToolkitError> {
+    let mut bytes = vec![TRIE_LEAF_TAG];
+    bytes.extend(serialize(key)?);
+    bytes.extend(serialize(value)?);
+    Ok(bytes)
+}
+
+fn full_node(pointer_at: impl Fn(usize) -> Pointer) -> Vec<(u8, Pointer)> {
+    (0..RADIX)
+        .map(|index| (index as u8, pointer_at(index)))
+        .collect()
+}
+
+// Pointer to a leaf not served by the trie.
+fn filler_pointer(group: usize, index: usize) -> Pointer {
+    Pointer::Leaf(Digest::hash(format!("filler {} {}", group, index)))
+}
+
+fn extension_affix(group: usize) -> Bytes {
+    Bytes::from(vec![group as u8, 0])
+}
+
+fn extension_hash(group: usize, pointers: &[(u8, Pointer)]) -> Result<Digest, ToolkitError> {
+    let node_pointer = Pointer::Node(Digest::hash_into_chunks_if_necessary(&node_bytes(pointers)));
+    let mut bytes = vec![TRIE_EXTENSION_TAG];
+    bytes.extend(serialize(&extension_affix(group))?);
+    write_pointer(&mut bytes, &node_pointer);
+
+    Ok(Digest::hash_into_chunks_if_necessary(&bytes))
+}
+
+// Writes the node without the pointer at the hole index, which is computed from the proof.
+fn write_node_step(
+    proof: &mut Vec<u8>,
+    pointers: &[(u8, Pointer)],
+    hole_index: usize,
+) -> Result<(), ToolkitError> {
+    proof.extend([PROOF_STEP_NODE_TAG, hole_index as u8]);
+    proof.extend(serialize(&(pointers.len() as u32 - 1))?);
+    for (index, pointer) in pointers {
+        if *index as usize != hole_index {
+            proof.push(*index);
+            write_pointer(proof, pointer);
+        }
+    }
+
+    Ok(())
+}
+
+fn serialize<T: ToBytes>(value: &T) -> Result<Vec<u8>, ToolkitError> {
+    value
+        .to_bytes()
+        .map_err(|_e| ToolkitError::SerializationError {
+            context: "state trie",
+        })
+}
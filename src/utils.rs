@@ -1,3 +1,5 @@
+use std::path::Path;
+
 use casper_event_standard::casper_types;
 use casper_types::contracts::NamedKeys;
 use casper_types::{Key, URef};
@@ -24,6 +26,17 @@ pub fn extract_uref_from_named_keys(
     Ok(*uref)
 }
 
+/// Replaces the file content.
+pub(crate) fn write_atomically(path: &Path, content: impl AsRef<[u8]>) -> Result<(), ToolkitError> {
+    // Write to temporary file first, so the file is never left half-written.
+    let mut temporary_path = path.to_path_buf().into_os_string();
+    temporary_path.push(".tmp");
+    std::fs::write(&temporary_path, content)?;
+    std::fs::rename(&temporary_path, path)?;
+
+    Ok(())
+}
+
 pub fn parse_hash(hash_str: &str) -> Result<[u8; 32], ToolkitError> {
     let bytes = hex::decode(hash_str).map_err(|_e| ToolkitError::InvalidHash {
         context: "hex parsing failed",